@@ -0,0 +1,135 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+use failure::{Error, ResultExt};
+
+use remarks::collect_remarks;
+use types::{to_value_tree, CompiledPiiConfig, SensitiveEvent, StrippedEvent};
+
+/// Statistics over a batch of events stripped with the same config.
+#[derive(Debug, Default)]
+pub struct BatchSummary {
+    pub events: usize,
+    pub parse_errors: usize,
+    pub strip_errors: usize,
+    /// How many values each rule touched, by rule id. A value counts once per rule, however many
+    /// remarks the rule left on it, and remarks the event already carried are not counted.
+    pub rules: BTreeMap<String, usize>,
+}
+
+impl BatchSummary {
+    /// Parses and strips a single event, recording the outcome in the summary.
+    pub fn strip_event(&mut self, config: &CompiledPiiConfig, json: &str) -> Result<StrippedEvent, Error> {
+        self.events += 1;
+
+        let event = match SensitiveEvent::from_json(json).context("Failed to parse event") {
+            Ok(event) => event,
+            Err(e) => {
+                self.parse_errors += 1;
+                return Err(e.into());
+            }
+        };
+
+        let stripped_event = match config.strip_event(&event) {
+            Ok(stripped_event) => stripped_event,
+            Err(e) => {
+                self.strip_errors += 1;
+                return Err(e);
+            }
+        };
+
+        let input_remarks: BTreeSet<_> = collect_remarks(&to_value_tree(event))
+            .into_iter()
+            .map(|(path, remark)| (path, remark.rule_id().to_owned()))
+            .collect();
+
+        let mut touched = BTreeSet::new();
+        for (path, remark) in collect_remarks(&stripped_event) {
+            let key = (path, remark.rule_id().to_owned());
+            if !input_remarks.contains(&key) {
+                touched.insert(key);
+            }
+        }
+
+        for (_, rule_id) in touched {
+            *self.rules.entry(rule_id).or_insert(0) += 1;
+        }
+
+        Ok(stripped_event)
+    }
+}
+
+impl fmt::Display for BatchSummary {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "Processed {} events: {} failed to parse, {} failed to strip",
+            self.events, self.parse_errors, self.strip_errors
+        )?;
+
+        if self.rules.is_empty() {
+            write!(f, "No rule touched any value")?;
+        } else {
+            write!(f, "Values touched per rule:")?;
+            for (rule, count) in &self.rules {
+                write!(f, "\n  {}: {}", rule, count)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use types::PiiConfig;
+
+    fn get_config() -> CompiledPiiConfig {
+        let config = json!({
+            "rules": {
+                "digits": {
+                    "type": "pattern",
+                    "pattern": "\\d+",
+                    "redaction": { "method": "replace", "text": "[n]" }
+                }
+            },
+            "applications": { "$string": ["digits"] }
+        });
+
+        PiiConfig::from_value(config).unwrap().compile().unwrap()
+    }
+
+    #[test]
+    fn test_counts_values_once_per_rule() {
+        let mut summary = BatchSummary::default();
+        let event = r#"{"extra": {"a": "1 and 2", "b": "3"}}"#;
+        summary.strip_event(&get_config(), event).unwrap();
+
+        assert_eq!(summary.events, 1);
+        assert_eq!(summary.rules.get("digits"), Some(&2));
+    }
+
+    #[test]
+    fn test_skips_input_remarks() {
+        let mut summary = BatchSummary::default();
+        let event = r#"{
+            "extra": {"a": "1", "b": "[n]"},
+            "_meta": {"extra": {"b": {"": {"rem": [["digits", "s", 0, 3]]}}}}
+        }"#;
+        summary.strip_event(&get_config(), event).unwrap();
+
+        assert_eq!(summary.rules.get("digits"), Some(&1));
+    }
+
+    #[test]
+    fn test_counts_errors() {
+        let mut summary = BatchSummary::default();
+        assert!(summary.strip_event(&get_config(), "{").is_err());
+
+        assert_eq!(summary.events, 1);
+        assert_eq!(summary.parse_errors, 1);
+        assert!(summary.rules.is_empty());
+    }
+}
@@ -0,0 +1,175 @@
+use std::env;
+use std::fs;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::process;
+
+use failure::{err_msg, Error, ResultExt};
+use relay_general::types::{Annotated, ToValue};
+use serde_json;
+
+use batch::BatchSummary;
+use config_format::parse_config_auto;
+use types::{PiiConfig, SensitiveEvent};
+
+static USAGE: &'static str = "Usage: piinguin --config <CONFIG> [--batch] [--meta] [--pretty] [--output <FILE>] [EVENT]
+
+Strips sensitive data from an event using a PII config. Reads the event from
+EVENT, or from stdin if EVENT is omitted or `-`.
+
+With --batch, EVENT is a JSON Lines file with one event per line. Every line
+is stripped separately and written out as `{\"line\": N, \"event\": ...}` or
+`{\"line\": N, \"error\": ...}`, followed by a summary on stderr.
+
+Options:
+    -c, --config <CONFIG>   Path to the PII config (JSON, YAML or TOML)
+    -o, --output <FILE>     Write the stripped event to FILE instead of stdout
+        --batch             Read one event per line and print a summary
+        --meta              Keep `_meta` with the remarks left by the PII processor
+        --pretty            Pretty-print the stripped event
+    -h, --help              Print this help";
+
+#[derive(Default)]
+struct Args {
+    config: String,
+    event: Option<String>,
+    output: Option<String>,
+    batch: bool,
+    meta: bool,
+    pretty: bool,
+}
+
+fn parse_args() -> Result<Args, Error> {
+    let mut args = Args::default();
+    let mut config = None;
+    let mut iter = env::args().skip(1);
+
+    while let Some(arg) = iter.next() {
+        match &*arg {
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                process::exit(0);
+            }
+            "-c" | "--config" => {
+                config = Some(iter.next().ok_or_else(|| err_msg("--config requires a value"))?);
+            }
+            "-o" | "--output" => {
+                args.output = Some(iter.next().ok_or_else(|| err_msg("--output requires a value"))?);
+            }
+            "--batch" => args.batch = true,
+            "--meta" => args.meta = true,
+            "--pretty" => args.pretty = true,
+            _ if arg.starts_with('-') && arg != "-" => {
+                return Err(err_msg(format!("Unknown option {}", arg)));
+            }
+            _ if args.event.is_none() => args.event = Some(arg),
+            _ => return Err(err_msg(format!("Unexpected argument {}", arg))),
+        }
+    }
+
+    args.config = config.ok_or_else(|| err_msg("--config is required"))?;
+    Ok(args)
+}
+
+fn open_input(path: Option<&str>) -> Result<Box<dyn Read>, Error> {
+    match path {
+        None | Some("-") => Ok(Box::new(io::stdin())),
+        Some(path) => Ok(Box::new(fs::File::open(path).context("Failed to open event")?)),
+    }
+}
+
+fn open_output(path: Option<&str>) -> Result<Box<dyn Write>, Error> {
+    match path {
+        None => Ok(Box::new(io::stdout())),
+        Some(path) => Ok(Box::new(fs::File::create(path).context("Failed to create output")?)),
+    }
+}
+
+fn serialize_event<T: ToValue>(stripped_event: &Annotated<T>, args: &Args) -> Result<String, Error> {
+    let output = match (args.meta, args.pretty) {
+        (true, true) => stripped_event.to_json_pretty(),
+        (true, false) => stripped_event.to_json(),
+        (false, true) => stripped_event.payload_to_json_pretty(),
+        (false, false) => stripped_event.payload_to_json(),
+    };
+
+    Ok(output.context("Failed to serialize stripped event")?)
+}
+
+fn run_single(args: &Args, config: &PiiConfig, input: &mut dyn Read, output: &mut dyn Write) -> Result<(), Error> {
+    let mut json = String::new();
+    input.read_to_string(&mut json).context("Failed to read event")?;
+
+    let event = SensitiveEvent::from_json(&json).context("Failed to parse event")?;
+    let stripped_event = config.compile()?.process_event(&event)?;
+
+    writeln!(output, "{}", serialize_event(&stripped_event, args)?).context("Failed to write output")?;
+    Ok(())
+}
+
+fn run_batch(args: &Args, config: &PiiConfig, input: &mut dyn Read, output: &mut dyn Write) -> Result<(), Error> {
+    let mut summary = BatchSummary::default();
+    let config = config.compile()?;
+
+    for (index, line) in BufReader::new(input).lines().enumerate() {
+        let line = line.context("Failed to read event")?;
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let result = match summary.strip_event(&config, &line) {
+            Ok(stripped_event) => {
+                let event: serde_json::Value = serde_json::from_str(&serialize_event(&stripped_event, args)?)?;
+                json!({ "line": index + 1, "event": event })
+            }
+            Err(e) => json!({ "line": index + 1, "error": format_error(&e) }),
+        };
+
+        let result = if args.pretty {
+            serde_json::to_string_pretty(&result)?
+        } else {
+            serde_json::to_string(&result)?
+        };
+        writeln!(output, "{}", result).context("Failed to write output")?;
+    }
+
+    eprintln!("{}", summary);
+    Ok(())
+}
+
+fn format_error(e: &Error) -> String {
+    let mut rv = e.to_string();
+    for cause in e.iter_causes() {
+        rv.push_str(&format!(": {}", cause));
+    }
+    rv
+}
+
+fn run(args: &Args) -> Result<(), Error> {
+    let (config, _) = parse_config_auto(&fs::read_to_string(&args.config).context("Failed to read config")?)?;
+
+    let mut input = open_input(args.event.as_ref().map(|x| &**x))?;
+    let mut output = open_output(args.output.as_ref().map(|x| &**x))?;
+
+    if args.batch {
+        run_batch(args, &config, &mut input, &mut output)
+    } else {
+        run_single(args, &config, &mut input, &mut output)
+    }
+}
+
+pub fn main() {
+    // Only mistakes on the command line are answered with the usage.
+    let args = match parse_args() {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("error: {}", format_error(&e));
+            eprintln!("\n{}", USAGE);
+            process::exit(1);
+        }
+    };
+
+    if let Err(e) = run(&args) {
+        eprintln!("error: {}", format_error(&e));
+        process::exit(1);
+    }
+}
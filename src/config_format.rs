@@ -0,0 +1,141 @@
+use std::fmt;
+
+use failure::{Error, ResultExt};
+use serde_json;
+use serde_yaml;
+use toml;
+
+use types::PiiConfig;
+
+/// A serialization format for PII configs.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ConfigFormat {
+    Json,
+    Yaml,
+    Toml,
+}
+
+impl ConfigFormat {
+    pub fn all() -> &'static [ConfigFormat] {
+        &[ConfigFormat::Json, ConfigFormat::Yaml, ConfigFormat::Toml]
+    }
+
+    /// Guesses the format of a config by checking which format it parses in.
+    pub fn detect(input: &str) -> Option<ConfigFormat> {
+        ConfigFormat::all()
+            .iter()
+            .cloned()
+            .find(|format| parse_config(input, *format).is_ok())
+    }
+}
+
+impl fmt::Display for ConfigFormat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ConfigFormat::Json => write!(f, "JSON"),
+            ConfigFormat::Yaml => write!(f, "YAML"),
+            ConfigFormat::Toml => write!(f, "TOML"),
+        }
+    }
+}
+
+/// Parses a PII config in the given format.
+pub fn parse_config(input: &str, format: ConfigFormat) -> Result<PiiConfig, Error> {
+    let map = match format {
+        ConfigFormat::Json => serde_json::from_str(input).context("Failed to parse JSON config")?,
+        ConfigFormat::Yaml => serde_yaml::from_str(input).context("Failed to parse YAML config")?,
+        ConfigFormat::Toml => toml::from_str(input).context("Failed to parse TOML config")?,
+    };
+
+    Ok(PiiConfig(map))
+}
+
+/// Parses a PII config in whichever format it is written in.
+///
+/// If the config does not parse in any format, this reports the JSON error for configs that look
+/// like JSON and the YAML error otherwise.
+pub fn parse_config_auto(input: &str) -> Result<(PiiConfig, ConfigFormat), Error> {
+    if let Some(format) = ConfigFormat::detect(input) {
+        return Ok((parse_config(input, format)?, format));
+    }
+
+    let format = if input.trim_start().starts_with('{') {
+        ConfigFormat::Json
+    } else {
+        ConfigFormat::Yaml
+    };
+
+    Err(parse_config(input, format).unwrap_err())
+}
+
+/// Parses a PII config in the given format, or in whichever format it is written in if `None`.
+#[cfg(feature = "web")]
+pub fn parse_config_as(input: &str, format: Option<ConfigFormat>) -> Result<PiiConfig, Error> {
+    match format {
+        Some(format) => parse_config(input, format),
+        None => Ok(parse_config_auto(input)?.0),
+    }
+}
+
+/// Serializes a PII config in the given format.
+#[cfg(any(feature = "web", test))]
+pub fn serialize_config(config: &PiiConfig, format: ConfigFormat) -> Result<String, Error> {
+    Ok(match format {
+        ConfigFormat::Json => serde_json::to_string_pretty(&config.0)?,
+        ConfigFormat::Yaml => serde_yaml::to_string(&config.0)?,
+        ConfigFormat::Toml => {
+            // Going through `toml::Value` puts tables after plain values, as TOML requires.
+            let value = toml::Value::try_from(&config.0).context("Config cannot be expressed in TOML")?;
+            toml::to_string_pretty(&value)?
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_config() -> PiiConfig {
+        let config = json!({
+            "rules": {
+                "device_id": {
+                    "type": "pattern",
+                    "pattern": "[a-f0-9]{32}",
+                    "redaction": { "method": "replace", "text": "[device]" }
+                }
+            },
+            "applications": { "$string": ["device_id", "@ip"] }
+        });
+
+        PiiConfig::from_value(config).unwrap()
+    }
+
+    #[test]
+    fn test_detect() {
+        assert_eq!(ConfigFormat::detect(r#"{"applications": {}}"#), Some(ConfigFormat::Json));
+        assert_eq!(ConfigFormat::detect("applications:\n  $string: [\"@ip\"]\n"), Some(ConfigFormat::Yaml));
+        assert_eq!(ConfigFormat::detect("[applications]\n\"$string\" = [\"@ip\"]\n"), Some(ConfigFormat::Toml));
+        assert_eq!(ConfigFormat::detect("{"), None);
+        assert_eq!(ConfigFormat::detect("just text"), None);
+    }
+
+    #[test]
+    fn test_serialize_config_roundtrip() {
+        let config = get_config();
+
+        for &format in ConfigFormat::all() {
+            let output = serialize_config(&config, format).unwrap();
+            assert_eq!(parse_config(&output, format).unwrap(), config, "{} config", format);
+            assert_eq!(ConfigFormat::detect(&output), Some(format), "{} config", format);
+        }
+    }
+
+    #[test]
+    fn test_serialize_config_toml_tables_last() {
+        // `redaction` sorts before `type`, but TOML needs the table after the plain values.
+        let output = serialize_config(&get_config(), ConfigFormat::Toml).unwrap();
+        let table = output.find("[rules.device_id.redaction]").unwrap();
+        let value = output.find("type = \"pattern\"").unwrap();
+        assert!(value < table, "{}", output);
+    }
+}
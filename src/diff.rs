@@ -0,0 +1,115 @@
+use std::cmp;
+
+use relay_general::types::{Annotated, Meta, RemarkType, Value};
+
+use types::join_path;
+
+/// How a value was changed by PII stripping.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ChangeKind {
+    Removed,
+    Masked,
+    Hashed,
+    Replaced,
+    Changed,
+}
+
+impl ChangeKind {
+    fn from_meta(meta: &Meta, removed: bool) -> ChangeKind {
+        for remark in meta.iter_remarks() {
+            match remark.ty() {
+                RemarkType::Removed => return ChangeKind::Removed,
+                RemarkType::Masked => return ChangeKind::Masked,
+                RemarkType::Pseudonymized => return ChangeKind::Hashed,
+                RemarkType::Substituted => return ChangeKind::Replaced,
+                _ => (),
+            }
+        }
+
+        if removed {
+            ChangeKind::Removed
+        } else {
+            ChangeKind::Changed
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ChangeKind::Removed => "removed",
+            ChangeKind::Masked => "masked",
+            ChangeKind::Hashed => "hashed",
+            ChangeKind::Replaced => "replaced",
+            ChangeKind::Changed => "changed",
+        }
+    }
+}
+
+/// A value that differs between the original and the stripped event.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Change {
+    pub path: String,
+    #[serde(with = "annotated_json")]
+    pub original: Annotated<Value>,
+    #[serde(with = "annotated_json")]
+    pub stripped: Annotated<Value>,
+    pub kind: ChangeKind,
+}
+
+/// Serializes annotated values as JSON strings including their meta.
+mod annotated_json {
+    use relay_general::types::{Annotated, Value};
+    use serde::{de, ser, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(value: &Annotated<Value>, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&value.to_json().map_err(ser::Error::custom)?)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Annotated<Value>, D::Error> {
+        Annotated::from_json(&String::deserialize(deserializer)?).map_err(de::Error::custom)
+    }
+}
+
+fn diff_at(original: &Annotated<Value>, stripped: &Annotated<Value>, path: &str, rv: &mut Vec<Change>) {
+    let empty = Annotated::empty();
+
+    match (original.value(), stripped.value()) {
+        (Some(Value::Object(original_map)), Some(Value::Object(stripped_map))) => {
+            let added_keys = stripped_map.keys().filter(|key| !original_map.contains_key(*key));
+            for key in original_map.keys().chain(added_keys) {
+                diff_at(
+                    original_map.get(key).unwrap_or(&empty),
+                    stripped_map.get(key).unwrap_or(&empty),
+                    &join_path(path, key),
+                    rv,
+                );
+            }
+        }
+        (Some(Value::Array(original_array)), Some(Value::Array(stripped_array))) => {
+            for index in 0..cmp::max(original_array.len(), stripped_array.len()) {
+                diff_at(
+                    original_array.get(index).unwrap_or(&empty),
+                    stripped_array.get(index).unwrap_or(&empty),
+                    &join_path(path, &index.to_string()),
+                    rv,
+                );
+            }
+        }
+        (original_value, stripped_value) => {
+            if original_value != stripped_value {
+                rv.push(Change {
+                    path: path.to_owned(),
+                    original: original.clone(),
+                    stripped: stripped.clone(),
+                    kind: ChangeKind::from_meta(stripped.meta(), stripped_value.is_none()),
+                });
+            }
+        }
+    }
+}
+
+/// Walks two value trees in parallel and returns all values that differ between them.
+pub fn diff_values(original: &Annotated<Value>, stripped: &Annotated<Value>) -> Vec<Change> {
+    let mut rv = vec![];
+    diff_at(original, stripped, "", &mut rv);
+    rv
+}
@@ -0,0 +1,198 @@
+use failure::{err_msg, Error, ResultExt};
+use serde_json;
+
+use types::SensitiveEvent;
+
+/// Envelope item types that contain an event payload.
+static EVENT_ITEM_TYPES: &[&'static str] = &["event", "transaction"];
+
+/// A single item of the input, which is either a bare event or one item of an envelope.
+pub enum InputItem {
+    Event {
+        ty: String,
+        event: SensitiveEvent,
+        /// The JSON the event was parsed from.
+        payload: String,
+    },
+    Other {
+        ty: String,
+        filename: Option<String>,
+        size: usize,
+    },
+}
+
+impl InputItem {
+    pub fn ty(&self) -> &str {
+        match *self {
+            InputItem::Event { ref ty, .. } => ty,
+            InputItem::Other { ref ty, .. } => ty,
+        }
+    }
+}
+
+/// Returns whether the input looks like an envelope rather than a bare event. Envelopes start with
+/// a line containing a complete JSON object, and contain further lines with items.
+pub fn is_envelope(input: &str) -> bool {
+    let mut lines = input.trim().lines();
+    let header = lines.next().unwrap_or("");
+
+    serde_json::from_str::<serde_json::Map<String, serde_json::Value>>(header).is_ok()
+        && lines.any(|line| !line.trim().is_empty())
+}
+
+fn split_line(input: &str) -> (&str, &str) {
+    match input.find('\n') {
+        Some(index) => (&input[..index], &input[index + 1..]),
+        None => (input, ""),
+    }
+}
+
+fn parse_item(ty: String, headers: &serde_json::Value, payload: &str) -> Result<InputItem, Error> {
+    if EVENT_ITEM_TYPES.contains(&&*ty) {
+        let event = SensitiveEvent::from_json(payload).context("Failed to parse event item")?;
+        return Ok(InputItem::Event {
+            ty,
+            event,
+            payload: payload.to_owned(),
+        });
+    }
+
+    Ok(InputItem::Other {
+        filename: headers.get("filename").and_then(|x| x.as_str()).map(|x| x.to_owned()),
+        size: payload.len(),
+        ty,
+    })
+}
+
+/// Parses all items out of an envelope. Items with a `length` header take exactly that many
+/// bytes of payload, all others extend to the end of the line.
+pub fn parse_envelope(input: &str) -> Result<Vec<InputItem>, Error> {
+    let (header, mut rest) = split_line(input);
+    serde_json::from_str::<serde_json::Value>(header).context("Failed to parse envelope header")?;
+
+    let mut items = vec![];
+
+    while !rest.trim().is_empty() {
+        let (item_header, after_header) = split_line(rest);
+        let headers: serde_json::Value =
+            serde_json::from_str(item_header).context("Failed to parse envelope item header")?;
+
+        let ty = headers
+            .get("type")
+            .and_then(|x| x.as_str())
+            .ok_or_else(|| err_msg("Envelope item header is missing the type"))?
+            .to_owned();
+
+        let (payload, after_payload) = match headers.get("length").and_then(|x| x.as_u64()) {
+            Some(length) => {
+                let length = length as usize;
+                if length > after_header.len() || !after_header.is_char_boundary(length) {
+                    return Err(err_msg(format!("Envelope item of type {} is truncated", ty)));
+                }
+                let (payload, after_payload) = after_header.split_at(length);
+                (payload, after_payload.trim_start_matches('\n'))
+            }
+            None => split_line(after_header),
+        };
+
+        items.push(parse_item(ty, &headers, payload)?);
+        rest = after_payload;
+    }
+
+    Ok(items)
+}
+
+/// Parses the event input, which may be a bare event or an envelope.
+pub fn parse_input(input: &str) -> Result<Vec<InputItem>, Error> {
+    if is_envelope(input) {
+        parse_envelope(input)
+    } else {
+        let event = SensitiveEvent::from_json(input).context("Failed to parse event")?;
+        Ok(vec![InputItem::Event {
+            ty: "event".to_owned(),
+            event,
+            payload: input.to_owned(),
+        }])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_envelope() {
+        assert!(is_envelope("{}\n{\"type\":\"event\"}\n{}\n"));
+        assert!(!is_envelope("{\"message\":\"hi\"}"));
+        assert!(!is_envelope("{\n  \"message\": \"hi\"\n}"));
+        assert!(!is_envelope("{}\n\n"));
+    }
+
+    #[test]
+    fn test_parse_envelope_length() {
+        let payload = "{\n  \"message\": \"hi\"\n}";
+        let input = format!(
+            "{{}}\n{{\"type\":\"event\",\"length\":{}}}\n{}\n{{\"type\":\"event\"}}\n{{\"message\":\"ho\"}}\n",
+            payload.len(),
+            payload
+        );
+
+        let items = parse_envelope(&input).unwrap();
+        assert_eq!(items.len(), 2);
+
+        match items[0] {
+            InputItem::Event {
+                ref event, ref payload, ..
+            } => {
+                assert_eq!(payload, "{\n  \"message\": \"hi\"\n}");
+                assert!(event.value().is_some());
+            }
+            InputItem::Other { .. } => panic!("expected an event"),
+        }
+
+        match items[1] {
+            InputItem::Event { ref payload, .. } => assert_eq!(payload, "{\"message\":\"ho\"}"),
+            InputItem::Other { .. } => panic!("expected an event"),
+        }
+    }
+
+    #[test]
+    fn test_parse_envelope_truncated() {
+        let input = "{}\n{\"type\":\"event\",\"length\":100}\n{\"message\":\"hi\"}\n";
+        let error = parse_envelope(input).err().unwrap();
+        assert_eq!(error.to_string(), "Envelope item of type event is truncated");
+    }
+
+    #[test]
+    fn test_parse_envelope_other_items() {
+        let input = "{}\n{\"type\":\"attachment\",\"filename\":\"log.txt\",\"length\":5}\nhello\n{\"type\":\"session\"}\n{\"sid\":\"x\"}\n";
+        let items = parse_envelope(input).unwrap();
+
+        assert_eq!(items.iter().map(InputItem::ty).collect::<Vec<_>>(), vec!["attachment", "session"]);
+
+        match items[0] {
+            InputItem::Other {
+                ref filename, size, ..
+            } => {
+                assert_eq!(filename.as_ref().map(|x| &**x), Some("log.txt"));
+                assert_eq!(size, 5);
+            }
+            InputItem::Event { .. } => panic!("expected an attachment"),
+        }
+
+        match items[1] {
+            InputItem::Other { ref filename, size, .. } => {
+                assert_eq!(*filename, None);
+                assert_eq!(size, 11);
+            }
+            InputItem::Event { .. } => panic!("expected a session"),
+        }
+    }
+
+    #[test]
+    fn test_parse_input_bare_event() {
+        let items = parse_input("{\"message\":\"hi\"}").unwrap();
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].ty(), "event");
+    }
+}
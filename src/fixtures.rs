@@ -0,0 +1,117 @@
+use failure::{Error, ResultExt};
+use relay_general::types::{Annotated, Value};
+use serde_json;
+
+use diff::{diff_values, Change};
+use types::{PiiConfig, SensitiveEvent};
+
+/// An event, a PII config and the output that stripping the event with the config is expected to
+/// produce.
+///
+/// The same format is used for fixtures saved in the browser and for `fixtures/*.json`, which are
+/// checked by `cargo test`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Fixture {
+    pub event: serde_json::Value,
+    pub config: serde_json::Value,
+    /// The stripped event, without `_meta`.
+    pub expected: serde_json::Value,
+}
+
+impl Fixture {
+    /// Records the current output of stripping `event` with `config`.
+    pub fn record(event: &SensitiveEvent, config: &PiiConfig) -> Result<Fixture, Error> {
+        let stripped = config.strip_event(event)?;
+
+        Ok(Fixture {
+            event: serde_json::from_str(&event.to_json()?)?,
+            config: serde_json::Value::Object(config.0.clone()),
+            expected: serde_json::from_str(&stripped.payload_to_json()?)?,
+        })
+    }
+
+    fn get_config(&self) -> Result<PiiConfig, Error> {
+        Ok(PiiConfig::from_value(self.config.clone()).context("Invalid fixture config")?)
+    }
+
+    /// Strips the event and returns all values that differ from the expected output. Only values
+    /// are compared, remarks are ignored.
+    ///
+    /// Pass a config to check whether an edited config still produces the expected output, or
+    /// `None` to use the config the fixture was recorded with.
+    pub fn run(&self, config: Option<&PiiConfig>) -> Result<Vec<Change>, Error> {
+        let event = SensitiveEvent::from_json(&self.event.to_string()).context("Invalid fixture event")?;
+        let expected =
+            Annotated::<Value>::from_json(&self.expected.to_string()).context("Invalid fixture output")?;
+
+        let actual = match config {
+            Some(config) => config.strip_event(&event)?,
+            None => self.get_config()?.strip_event(&event)?,
+        };
+
+        Ok(diff_values(&expected, &actual))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::Path;
+
+    use super::*;
+
+    fn format_changes(changes: &[Change]) -> String {
+        changes
+            .iter()
+            .map(|change| {
+                format!(
+                    "  {}: expected {}, got {} ({})",
+                    change.path,
+                    change.original.payload_to_json().unwrap_or_default(),
+                    change.stripped.payload_to_json().unwrap_or_default(),
+                    change.kind.as_str()
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    #[test]
+    fn test_fixtures() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("fixtures");
+        let mut failures = vec![];
+
+        for entry in fs::read_dir(&dir).unwrap() {
+            let path = entry.unwrap().path();
+            if path.extension().and_then(|x| x.to_str()) != Some("json") {
+                continue;
+            }
+
+            let fixture: Fixture = serde_json::from_reader(fs::File::open(&path).unwrap()).unwrap();
+
+            match fixture.run(None) {
+                Ok(ref changes) if changes.is_empty() => (),
+                Ok(changes) => failures.push(format!("{}:\n{}", path.display(), format_changes(&changes))),
+                Err(e) => failures.push(format!("{}: {}", path.display(), e)),
+            }
+        }
+
+        assert!(
+            failures.is_empty(),
+            "{} fixtures produce different output:\n\n{}",
+            failures.len(),
+            failures.join("\n\n")
+        );
+    }
+
+    #[test]
+    fn test_record() {
+        let event = SensitiveEvent::from_json(r#"{"extra": {"ip": "127.0.0.1"}}"#).unwrap();
+        let config = PiiConfig::from_value(json!({"applications": {"$string": ["@ip:replace"]}})).unwrap();
+
+        let fixture = Fixture::record(&event, &config).unwrap();
+        assert_eq!(fixture.expected["extra"]["ip"], json!("[ip]"));
+        assert!(fixture.expected.get("_meta").is_none());
+        assert!(fixture.run(None).unwrap().is_empty());
+    }
+}
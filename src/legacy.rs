@@ -0,0 +1,185 @@
+use failure::{Error, ResultExt};
+use regex;
+use serde_json;
+
+use suggestions::{quote_segment, PiiConfigExt};
+use types::PiiConfig;
+
+/// Fields the legacy data scrubber filtered when its defaults were enabled.
+static DEFAULT_FIELDS: &[&'static str] = &[
+    "password",
+    "secret",
+    "passwd",
+    "api_key",
+    "apikey",
+    "access_token",
+    "auth",
+    "credentials",
+    "mysql_pwd",
+    "stripetoken",
+    "card[number]",
+];
+
+/// The legacy data scrubbing options of a project, with the names the Sentry project API uses.
+/// The names Relay uses for them are accepted as well.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct LegacySettings {
+    #[serde(alias = "scrubData")]
+    pub data_scrubber: bool,
+    #[serde(alias = "scrubDefaults")]
+    pub data_scrubber_defaults: bool,
+    #[serde(rename = "scrubIPAddresses", alias = "scrubIpAddresses")]
+    pub scrub_ip_addresses: bool,
+    pub sensitive_fields: Vec<String>,
+    #[serde(alias = "excludeFields")]
+    pub safe_fields: Vec<String>,
+}
+
+impl Default for LegacySettings {
+    fn default() -> LegacySettings {
+        LegacySettings {
+            data_scrubber: true,
+            data_scrubber_defaults: true,
+            scrub_ip_addresses: false,
+            sensitive_fields: vec![],
+            safe_fields: vec![],
+        }
+    }
+}
+
+impl LegacySettings {
+    pub fn from_json(input: &str) -> Result<LegacySettings, Error> {
+        Ok(serde_json::from_str(input).context("Invalid legacy data scrubbing settings")?)
+    }
+
+    /// Builds a PII config that scrubs the same values as these settings.
+    ///
+    /// Sensitive fields become a `redact_pair` rule matching keys that contain any of them, like
+    /// the legacy scrubber did. Safe fields are excluded from the selector all rules apply to. IP
+    /// addresses are scrubbed independently of the data scrubber, like in Sentry.
+    pub fn to_pii_config(&self) -> Result<PiiConfig, Error> {
+        let mut config = PiiConfig(serde_json::Map::new());
+        let mut rules = vec![];
+
+        if self.data_scrubber && self.data_scrubber_defaults {
+            rules.push("@common:filter".to_owned());
+        }
+
+        if self.scrub_ip_addresses {
+            rules.push("@ip:replace".to_owned());
+        }
+
+        let mut fields: Vec<String> = vec![];
+        let defaults: &[&str] = if self.data_scrubber_defaults { DEFAULT_FIELDS } else { &[] };
+        let custom = self.sensitive_fields.iter().map(|x| x.as_str());
+
+        for field in defaults.iter().cloned().chain(custom) {
+            let field = field.trim().to_lowercase();
+            if !field.is_empty() && !fields.contains(&field) {
+                fields.push(field);
+            }
+        }
+
+        if self.data_scrubber && !fields.is_empty() {
+            let rule = "strip-fields".to_owned();
+            let pattern = fields.iter().map(|x| regex::escape(x)).collect::<Vec<_>>().join("|");
+
+            config.create_rule(
+                &rule,
+                json!({
+                    "type": "redact_pair",
+                    "keyPattern": format!("(?i).*({}).*", pattern),
+                    "redaction": {
+                        "method": "replace",
+                        "text": "[Filtered]",
+                    },
+                }),
+            )?;
+            rules.push(rule);
+        }
+
+        let mut selector = "($string || $number || $array)".to_owned();
+        for field in &self.safe_fields {
+            let field = field.trim();
+            if !field.is_empty() {
+                selector.push_str(&format!(" && !**.{}", quote_segment(field)));
+            }
+        }
+
+        for rule in &rules {
+            config.add_rule(&selector, rule)?;
+        }
+
+        Ok(config)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use types::SensitiveEvent;
+
+    fn get_rules(config: &PiiConfig) -> Vec<String> {
+        let mut rules: Vec<String> = config
+            .0
+            .get("applications")
+            .and_then(|x| x.as_object())
+            .into_iter()
+            .flat_map(|applications| applications.values())
+            .filter_map(|rules| rules.as_array())
+            .flatten()
+            .filter_map(|rule| rule.as_str())
+            .map(|rule| rule.to_owned())
+            .collect();
+
+        rules.sort();
+        rules
+    }
+
+    #[test]
+    fn test_flag_combinations() {
+        let cases: &[(&str, &[&str])] = &[
+            (r#"{}"#, &["@common:filter", "strip-fields"]),
+            (
+                r#"{"dataScrubber": true, "dataScrubberDefaults": true, "scrubIPAddresses": true}"#,
+                &["@common:filter", "@ip:replace", "strip-fields"],
+            ),
+            (r#"{"dataScrubber": true, "dataScrubberDefaults": false}"#, &[]),
+            (
+                r#"{"dataScrubber": true, "dataScrubberDefaults": false, "sensitiveFields": ["foo"]}"#,
+                &["strip-fields"],
+            ),
+            (
+                r#"{"dataScrubber": true, "dataScrubberDefaults": false, "scrubIPAddresses": true}"#,
+                &["@ip:replace"],
+            ),
+            (r#"{"dataScrubber": false, "scrubIPAddresses": true}"#, &["@ip:replace"]),
+            (r#"{"dataScrubber": false, "sensitiveFields": ["foo"]}"#, &[]),
+            (r#"{"dataScrubber": false, "scrubIPAddresses": false}"#, &[]),
+            (
+                r#"{"scrubData": true, "scrubDefaults": false, "scrubIpAddresses": true}"#,
+                &["@ip:replace"],
+            ),
+        ];
+
+        for &(settings, expected) in cases {
+            let config = LegacySettings::from_json(settings).unwrap().to_pii_config().unwrap();
+            assert_eq!(get_rules(&config), expected, "{}", settings);
+            config.compile().unwrap();
+        }
+    }
+
+    #[test]
+    fn test_safe_fields() {
+        let settings = r#"{"dataScrubberDefaults": false, "sensitiveFields": ["secret"], "safeFields": ["it's_secret"]}"#;
+        let config = LegacySettings::from_json(settings).unwrap().to_pii_config().unwrap();
+
+        let event = SensitiveEvent::from_json(r#"{"extra": {"secret": "a", "it's_secret": "b"}}"#).unwrap();
+        let stripped = config.strip_event(&event).unwrap().payload_to_json().unwrap();
+        let stripped: serde_json::Value = serde_json::from_str(&stripped).unwrap();
+
+        assert_eq!(stripped["extra"]["secret"], json!("[Filtered]"));
+        assert_eq!(stripped["extra"]["it's_secret"], json!("b"));
+    }
+}
@@ -0,0 +1,194 @@
+use std::collections::BTreeSet;
+use std::fmt;
+
+use failure::Error;
+
+use suggestions::get_selector_matches;
+use types::{get_rule_references, PiiConfig, SensitiveEvent};
+
+/// A problem found by linting a PII config.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Lint {
+    /// A rule in `rules` that nothing refers to.
+    UnusedRule { rule: String },
+    /// Aliases or `multiple` rules that refer to each other in a loop.
+    RuleCycle { rules: Vec<String> },
+    /// A selector in `applications` that matches no value in the events.
+    UnmatchedSelector { selector: String },
+    /// A selector in `applications` that cannot be applied.
+    InvalidSelector { selector: String, error: String },
+}
+
+impl fmt::Display for Lint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Lint::UnusedRule { ref rule } => write!(f, "Rule {} is never used", rule),
+            Lint::RuleCycle { ref rules } => write!(f, "Rules refer to each other: {} → {}", rules.join(" → "), rules[0]),
+            Lint::UnmatchedSelector { ref selector } => write!(f, "Selector {} matches nothing", selector),
+            Lint::InvalidSelector {
+                ref selector,
+                ref error,
+            } => write!(f, "Selector {} is invalid: {}", selector, error),
+        }
+    }
+}
+
+fn get_custom_rules(config: &PiiConfig) -> Vec<String> {
+    config
+        .0
+        .get("rules")
+        .and_then(|x| x.as_object())
+        .map(|rules| rules.keys().cloned().collect())
+        .unwrap_or_default()
+}
+
+fn find_cycles(config: &PiiConfig, rule: &str, stack: &mut Vec<String>, cycles: &mut BTreeSet<Vec<String>>) {
+    if let Some(index) = stack.iter().position(|x| x == rule) {
+        let mut cycle = stack[index..].to_vec();
+        // Rotate the cycle so that every cycle is reported once, no matter where it was entered.
+        let min_index = (0..cycle.len()).min_by_key(|&i| &cycle[i]).unwrap_or(0);
+        cycle.rotate_left(min_index);
+        cycles.insert(cycle);
+        return;
+    }
+
+    let references = match config.get_rule(rule) {
+        Some(spec) => get_rule_references(spec),
+        None => return,
+    };
+
+    stack.push(rule.to_owned());
+    for reference in references {
+        find_cycles(config, reference, stack, cycles);
+    }
+    stack.pop();
+}
+
+/// Lints a config for rules that are never used, cycles between rules, and selectors that match
+/// nothing in any of the given events.
+///
+/// References to rules that do not exist are errors in the config, which `validate_config`
+/// reports.
+pub fn lint_config(config: &PiiConfig, events: &[SensitiveEvent]) -> Result<Vec<Lint>, Error> {
+    let mut rv = vec![];
+    let custom_rules = get_custom_rules(config);
+    let applications = config.get_applications();
+
+    let mut used_rules = BTreeSet::new();
+    let mut references: Vec<(String, String)> = applications.clone();
+    for rule in &custom_rules {
+        for reference in config.get_rule(rule).map(get_rule_references).unwrap_or_default() {
+            references.push((rule.clone(), reference.to_owned()));
+        }
+    }
+
+    for (referrer, rule) in references {
+        if referrer != rule {
+            used_rules.insert(rule);
+        }
+    }
+
+    for rule in &custom_rules {
+        if !used_rules.contains(rule) {
+            rv.push(Lint::UnusedRule { rule: rule.clone() });
+        }
+    }
+
+    let mut cycles = BTreeSet::new();
+    for rule in &custom_rules {
+        find_cycles(config, rule, &mut vec![], &mut cycles);
+    }
+    rv.extend(cycles.into_iter().map(|rules| Lint::RuleCycle { rules }));
+
+    if !events.is_empty() {
+        let selectors: BTreeSet<String> = applications.into_iter().map(|(selector, _)| selector).collect();
+
+        'selectors: for selector in selectors {
+            for event in events {
+                match get_selector_matches(event, &selector) {
+                    Ok(ref matches) if matches.is_empty() => (),
+                    Ok(_) => continue 'selectors,
+                    Err(e) => {
+                        rv.push(Lint::InvalidSelector {
+                            selector,
+                            error: e.to_string(),
+                        });
+                        continue 'selectors;
+                    }
+                }
+            }
+
+            rv.push(Lint::UnmatchedSelector { selector });
+        }
+    }
+
+    Ok(rv)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json;
+
+    use super::*;
+
+    fn get_config(rules: serde_json::Value) -> PiiConfig {
+        PiiConfig::from_value(json!({ "rules": rules })).unwrap()
+    }
+
+    fn get_cycles(config: &PiiConfig) -> Vec<Vec<String>> {
+        let mut cycles = BTreeSet::new();
+        for rule in get_custom_rules(config) {
+            find_cycles(config, &rule, &mut vec![], &mut cycles);
+        }
+        cycles.into_iter().collect()
+    }
+
+    #[test]
+    fn test_find_cycles() {
+        let config = get_config(json!({
+            "a": { "type": "alias", "rule": "b" },
+            "b": { "type": "alias", "rule": "a" },
+            "c": { "type": "multiple", "rules": ["@ip", "d"] },
+            "d": { "type": "alias", "rule": "c" },
+            "e": { "type": "alias", "rule": "e" },
+            "f": { "type": "alias", "rule": "@email" },
+        }));
+
+        assert_eq!(
+            get_cycles(&config),
+            vec![
+                vec!["a".to_owned(), "b".to_owned()],
+                vec!["c".to_owned(), "d".to_owned()],
+                vec!["e".to_owned()],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_find_cycles_none() {
+        let config = get_config(json!({
+            "a": { "type": "multiple", "rules": ["b", "c"] },
+            "b": { "type": "alias", "rule": "c" },
+            "c": { "type": "ip" },
+        }));
+
+        assert!(get_cycles(&config).is_empty());
+    }
+
+    #[test]
+    fn test_lint_config_reports_cycle_once() {
+        let config = get_config(json!({
+            "b": { "type": "alias", "rule": "a" },
+            "a": { "type": "alias", "rule": "b" },
+        }));
+
+        let lints = lint_config(&config, &[]).unwrap();
+        assert_eq!(
+            lints,
+            vec![Lint::RuleCycle {
+                rules: vec!["a".to_owned(), "b".to_owned()]
+            }]
+        );
+        assert_eq!(lints[0].to_string(), "Rules refer to each other: a \u{2192} b \u{2192} a");
+    }
+}
@@ -0,0 +1,85 @@
+//! Sharing the playground state through the URL fragment. The fragment is never sent to a server,
+//! so permalinks keep the promise that nothing leaves the browser.
+
+use std::io::{Read, Write};
+
+use base64;
+use failure::{err_msg, Error, ResultExt};
+use flate2::read::DeflateDecoder;
+use flate2::write::DeflateEncoder;
+use flate2::Compression;
+use serde_json;
+use stdweb::web::window;
+
+/// Compresses the event and config into a string that is safe to use in a URL fragment.
+pub fn encode(event: &str, config: &str) -> Result<String, Error> {
+    let state = json!({ "event": event, "config": config });
+
+    let mut encoder = DeflateEncoder::new(Vec::new(), Compression::best());
+    serde_json::to_writer(&mut encoder, &state)?;
+    let compressed = encoder.finish()?;
+
+    Ok(base64::encode_config(&compressed, base64::URL_SAFE_NO_PAD))
+}
+
+/// Restores the event and config from a string created by `encode`.
+pub fn decode(fragment: &str) -> Result<(String, String), Error> {
+    let compressed = base64::decode_config(fragment, base64::URL_SAFE_NO_PAD).context("Bad permalink encoding")?;
+
+    let mut json = String::new();
+    DeflateDecoder::new(&compressed[..])
+        .read_to_string(&mut json)
+        .context("Bad permalink compression")?;
+
+    let state: serde_json::Value = serde_json::from_str(&json).context("Bad permalink state")?;
+    let get_field = |key| {
+        state
+            .get(key)
+            .and_then(|value| value.as_str())
+            .map(|value| value.to_owned())
+            .ok_or_else(|| err_msg(format!("Permalink is missing {}", key)))
+    };
+
+    Ok((get_field("event")?, get_field("config")?))
+}
+
+/// Returns the event and config encoded in the current URL, if any.
+pub fn load() -> Option<(String, String)> {
+    let hash = window().location()?.hash().ok()?;
+    let fragment = hash.trim_start_matches('#');
+    if fragment.is_empty() {
+        return None;
+    }
+
+    decode(fragment).ok()
+}
+
+/// Removes the event and config from the current URL, so that reloading the page shows the latest
+/// edits instead of the link.
+pub fn clear() {
+    js! { @(no_return)
+        window.history.replaceState(null, "", window.location.pathname + window.location.search);
+    }
+}
+
+/// Copies a link to the current page with the event and config to the clipboard. The URL of the
+/// page itself is left alone, so that it does not override later edits on reload.
+pub fn copy_link(event: &str, config: &str) -> Result<(), Error> {
+    let href = window()
+        .location()
+        .and_then(|location| location.href().ok())
+        .ok_or_else(|| err_msg("Cannot access the current URL"))?;
+    let base = href.split('#').next().unwrap_or("");
+    let url = format!("{}#{}", base, encode(event, config)?);
+
+    js! { @(no_return)
+        var url = @{url};
+        if (navigator.clipboard) {
+            navigator.clipboard.writeText(url);
+        } else {
+            window.prompt("Copy this link:", url);
+        }
+    }
+
+    Ok(())
+}
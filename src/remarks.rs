@@ -0,0 +1,111 @@
+use relay_general::types::{Annotated, Remark, Value};
+
+use types::join_path;
+
+#[cfg(any(feature = "web", test))]
+use relay_general::types::RemarkType;
+
+/// Returns a human-readable name for the type of a remark.
+#[cfg(feature = "web")]
+pub fn remark_type_name(ty: RemarkType) -> &'static str {
+    match ty {
+        RemarkType::Annotated => "annotated",
+        RemarkType::Removed => "removed",
+        RemarkType::Substituted => "substituted",
+        RemarkType::Masked => "masked",
+        RemarkType::Pseudonymized => "pseudonymized",
+        RemarkType::Encrypted => "encrypted",
+    }
+}
+
+/// Splits a string into consecutive segments along the byte ranges of the given remarks. Every
+/// segment is paired with the remark covering it, if any.
+#[cfg(any(feature = "web", test))]
+pub fn split_by_remarks<'a>(string: &str, remarks: &'a [Remark]) -> Vec<(String, Option<&'a Remark>)> {
+    let mut rv: Vec<(String, Option<usize>)> = vec![];
+
+    for (position, c) in string.char_indices() {
+        let index = remarks.iter().position(|remark| match remark.range() {
+            Some(&(start, end)) => start <= position && position < end,
+            None => false,
+        });
+
+        match rv.last_mut() {
+            Some(&mut (ref mut segment, last_index)) if last_index == index => segment.push(c),
+            _ => rv.push((c.to_string(), index)),
+        }
+    }
+
+    rv.into_iter()
+        .map(|(segment, index)| (segment, index.map(|index| &remarks[index])))
+        .collect()
+}
+
+fn collect_remarks_at(value: &Annotated<Value>, path: &str, rv: &mut Vec<(String, Remark)>) {
+    for remark in value.meta().iter_remarks() {
+        rv.push((path.to_owned(), remark.clone()));
+    }
+
+    match value.value() {
+        Some(Value::Object(map)) => {
+            for (key, child) in map {
+                collect_remarks_at(child, &join_path(path, key), rv);
+            }
+        }
+        Some(Value::Array(array)) => {
+            for (index, child) in array.iter().enumerate() {
+                collect_remarks_at(child, &join_path(path, &index.to_string()), rv);
+            }
+        }
+        _ => (),
+    }
+}
+
+/// Collects all remarks the PII processor left in a stripped event, along with the path of the
+/// value they are attached to.
+pub fn collect_remarks(value: &Annotated<Value>) -> Vec<(String, Remark)> {
+    let mut rv = vec![];
+    collect_remarks_at(value, "", &mut rv);
+    rv
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_segments<'a>(string: &str, remarks: &'a [Remark]) -> Vec<(String, Option<&'a str>)> {
+        split_by_remarks(string, remarks)
+            .into_iter()
+            .map(|(segment, remark)| (segment, remark.map(|x| x.rule_id())))
+            .collect()
+    }
+
+    #[test]
+    fn test_split_by_remarks() {
+        let remarks = vec![Remark::with_range(RemarkType::Masked, "@ip", (4, 13))];
+        assert_eq!(
+            get_segments("ip: 127.0.0.1 end", &remarks),
+            vec![
+                ("ip: ".to_owned(), None),
+                ("127.0.0.1".to_owned(), Some("@ip")),
+                (" end".to_owned(), None),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_split_by_remarks_multibyte() {
+        // Ranges are byte offsets, and the euro sign takes three bytes.
+        let remarks = vec![Remark::with_range(RemarkType::Masked, "@ip", (4, 13))];
+        assert_eq!(
+            get_segments("\u{20ac} 127.0.0.1", &remarks),
+            vec![("\u{20ac} ".to_owned(), None), ("127.0.0.1".to_owned(), Some("@ip"))]
+        );
+    }
+
+    #[test]
+    fn test_split_by_remarks_without_range() {
+        let remarks = vec![Remark::new(RemarkType::Removed, "@password")];
+        assert_eq!(get_segments("secret", &remarks), vec![("secret".to_owned(), None)]);
+    }
+}
@@ -0,0 +1,265 @@
+use failure::{err_msg, Error};
+use regex::Regex;
+use serde_json;
+
+use suggestions::PiiConfigExt;
+use types::{get_rule_references, PiiConfig};
+
+pub static RULE_TYPES: &[&'static str] = &["pattern", "redact_pair", "alias", "multiple"];
+
+/// A field of the rule authoring form.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RuleField {
+    Id,
+    Type,
+    Selector,
+    Pattern,
+    KeyPattern,
+    Rule,
+    Rules,
+    HideInner,
+    Method,
+    Text,
+    Key,
+    MaskChar,
+}
+
+/// The state of the form for creating or editing a custom rule.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RuleForm {
+    pub id: String,
+    pub ty: String,
+    /// The selector in `applications` the rule is applied to, if any.
+    pub selector: String,
+    pub pattern: String,
+    pub key_pattern: String,
+    /// The rule an `alias` rule refers to.
+    pub rule: String,
+    /// The comma-separated rules of a `multiple` rule.
+    pub rules: String,
+    pub hide_inner: bool,
+    pub method: String,
+    pub text: String,
+    pub key: String,
+    pub mask_char: String,
+    /// The id and selector under which the form was last written to the config.
+    pub written: Option<(String, String)>,
+    pub error: Option<String>,
+}
+
+fn get_str(value: &serde_json::Value, key: &str) -> String {
+    value.get(key).and_then(|x| x.as_str()).unwrap_or("").to_owned()
+}
+
+fn check_regex(pattern: &str, field: &str) -> Result<(), Error> {
+    // An empty pattern matches everything.
+    if pattern.is_empty() {
+        return Err(err_msg(format!("The rule needs a {}", field)));
+    }
+    Regex::new(pattern).map_err(|e| err_msg(format!("Invalid {}: {}", field, e)))?;
+    Ok(())
+}
+
+/// Replaces the id in a rule reference, or in a list of them.
+fn rename_reference(value: &mut serde_json::Value, old_id: &str, new_id: &str) {
+    match *value {
+        serde_json::Value::String(ref mut id) if id == old_id => *id = new_id.to_owned(),
+        serde_json::Value::Array(ref mut ids) => {
+            for id in ids {
+                rename_reference(id, old_id, new_id);
+            }
+        }
+        _ => (),
+    }
+}
+
+/// Points all references to a rule in other rules and in `applications` to its new id.
+fn rename_references(config: &mut PiiConfig, old_id: &str, new_id: &str) {
+    if let Some(rules) = config.0.get_mut("rules").and_then(|x| x.as_object_mut()) {
+        for rule in rules.values_mut() {
+            let key = match rule.get("type").and_then(|x| x.as_str()) {
+                Some("alias") => "rule",
+                Some("multiple") => "rules",
+                _ => continue,
+            };
+
+            if let Some(reference) = rule.get_mut(key) {
+                rename_reference(reference, old_id, new_id);
+            }
+        }
+    }
+
+    if let Some(applications) = config.0.get_mut("applications").and_then(|x| x.as_object_mut()) {
+        for rules in applications.values_mut() {
+            rename_reference(rules, old_id, new_id);
+        }
+    }
+}
+
+impl RuleForm {
+    /// Creates a form for a new pattern rule with an id that is not taken yet.
+    pub fn new(config: &PiiConfig) -> RuleForm {
+        RuleForm {
+            id: config.get_unused_rule_id("rule"),
+            ty: "pattern".to_owned(),
+            selector: "$string".to_owned(),
+            pattern: String::new(),
+            key_pattern: String::new(),
+            rule: String::new(),
+            rules: String::new(),
+            hide_inner: false,
+            method: "replace".to_owned(),
+            text: "[redacted]".to_owned(),
+            key: String::new(),
+            mask_char: "*".to_owned(),
+            written: None,
+            error: None,
+        }
+    }
+
+    /// Creates a form for editing an existing custom rule.
+    pub fn from_rule(config: &PiiConfig, id: &str) -> Option<RuleForm> {
+        let spec = config.get_rule(id)?;
+        let redaction = spec.get("redaction").cloned().unwrap_or(json!({}));
+        let selector = config
+            .get_applications()
+            .into_iter()
+            .find(|(_, rule)| rule == id)
+            .map(|(selector, _)| selector)
+            .unwrap_or_default();
+
+        Some(RuleForm {
+            id: id.to_owned(),
+            ty: get_str(spec, "type"),
+            selector: selector.clone(),
+            pattern: get_str(spec, "pattern"),
+            key_pattern: get_str(spec, "keyPattern"),
+            rule: get_str(spec, "rule"),
+            rules: match spec.get("type").and_then(|x| x.as_str()) {
+                Some("multiple") => get_rule_references(spec).join(", "),
+                _ => String::new(),
+            },
+            hide_inner: spec.get("hideInner").and_then(|x| x.as_bool()).unwrap_or(false),
+            method: match get_str(&redaction, "method") {
+                ref method if method.is_empty() => "default".to_owned(),
+                method => method,
+            },
+            text: get_str(&redaction, "text"),
+            key: get_str(&redaction, "key"),
+            mask_char: match get_str(&redaction, "maskChar") {
+                ref mask_char if mask_char.is_empty() => "*".to_owned(),
+                mask_char => mask_char,
+            },
+            written: Some((id.to_owned(), selector)),
+            error: None,
+        })
+    }
+
+    pub fn set(&mut self, field: RuleField, value: String) {
+        match field {
+            RuleField::Id => self.id = value,
+            RuleField::Type => self.ty = value,
+            RuleField::Selector => self.selector = value,
+            RuleField::Pattern => self.pattern = value,
+            RuleField::KeyPattern => self.key_pattern = value,
+            RuleField::Rule => self.rule = value,
+            RuleField::Rules => self.rules = value,
+            RuleField::HideInner => self.hide_inner = value == "true",
+            RuleField::Method => self.method = value,
+            RuleField::Text => self.text = value,
+            RuleField::Key => self.key = value,
+            RuleField::MaskChar => self.mask_char = value,
+        }
+    }
+
+    fn get_redaction(&self) -> Result<serde_json::Value, Error> {
+        Ok(match &*self.method {
+            "default" => json!({ "method": "default" }),
+            "remove" => json!({ "method": "remove" }),
+            "replace" => json!({ "method": "replace", "text": self.text }),
+            "mask" => {
+                let mut chars = self.mask_char.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(mask_char), None) => json!({ "method": "mask", "maskChar": mask_char.to_string() }),
+                    _ => return Err(err_msg("The mask char must be a single character")),
+                }
+            }
+            "hash" if self.key.is_empty() => json!({ "method": "hash" }),
+            "hash" => json!({ "method": "hash", "key": self.key }),
+            method => return Err(err_msg(format!("Unknown redaction method {}", method))),
+        })
+    }
+
+    /// Builds the rule definition for the `rules` map of a PII config.
+    pub fn to_rule(&self) -> Result<serde_json::Value, Error> {
+        let mut rule = match &*self.ty {
+            "pattern" => {
+                check_regex(&self.pattern, "pattern")?;
+                json!({ "type": "pattern", "pattern": self.pattern })
+            }
+            "redact_pair" => {
+                check_regex(&self.key_pattern, "key pattern")?;
+                json!({ "type": "redact_pair", "keyPattern": self.key_pattern })
+            }
+            "alias" if self.rule.is_empty() => return Err(err_msg("An alias needs a rule")),
+            "alias" => json!({ "type": "alias", "rule": self.rule, "hideInner": self.hide_inner }),
+            "multiple" => {
+                let rules: Vec<&str> = self
+                    .rules
+                    .split(',')
+                    .map(|x| x.trim())
+                    .filter(|x| !x.is_empty())
+                    .collect();
+                if rules.is_empty() {
+                    return Err(err_msg("A multiple rule needs at least one rule"));
+                }
+                json!({ "type": "multiple", "rules": rules, "hideInner": self.hide_inner })
+            }
+            ty => return Err(err_msg(format!("Unknown rule type {}", ty))),
+        };
+
+        rule["redaction"] = self.get_redaction()?;
+        Ok(rule)
+    }
+
+    /// Writes the rule into a config, replacing the version of it that was written before.
+    pub fn write(&mut self, mut config: PiiConfig) -> Result<PiiConfig, Error> {
+        let result = self.try_write(&mut config);
+        self.error = result.as_ref().err().map(|e| e.to_string());
+        result.map(|_| config)
+    }
+
+    fn try_write(&mut self, config: &mut PiiConfig) -> Result<(), Error> {
+        let id = self.id.trim().to_owned();
+        if id.is_empty() {
+            return Err(err_msg("The rule needs an id"));
+        }
+        let rule = self.to_rule()?;
+
+        if let Some((ref old_id, ref old_selector)) = self.written {
+            config.delete_rule(old_id)?;
+            if !old_selector.is_empty() {
+                config.remove_rule(old_selector, old_id)?;
+            }
+        }
+
+        if !config.create_rule(&id, rule)? {
+            return Err(err_msg(format!("A rule with id {} already exists", id)));
+        }
+
+        // Other rules and selectors keep referring to the rule after it was renamed.
+        if let Some((ref old_id, _)) = self.written {
+            if *old_id != id {
+                rename_references(config, old_id, &id);
+            }
+        }
+
+        let selector = self.selector.trim().to_owned();
+        if !selector.is_empty() {
+            config.add_rule(&selector, &id)?;
+        }
+
+        self.written = Some((id, selector));
+        Ok(())
+    }
+}
@@ -1,11 +1,14 @@
 use std::str::FromStr;
 
 use relay_general::pii::BUILTIN_RULES;
-use relay_general::types::{Annotated, Value};
+use regex;
+use relay_general::types::{Annotated, Object, Value};
 use serde_json;
 
 use failure::{err_msg, Error};
 
+use diff::{diff_values, Change};
+use remarks::collect_remarks;
 use types::*;
 
 static PII_KINDS: &[&'static str] = &[
@@ -28,7 +31,107 @@ static PII_KINDS: &[&'static str] = &[
     "$sdk",
 ];
 
-fn get_value_by_path<'a>(value: &'a Annotated<Value>, path: &str) -> Option<&'a Annotated<Value>> {
+/// PII kinds that only match values of one type, and nothing they contain.
+static SCALAR_KINDS: &[&'static str] = &["$string", "$number", "$boolean"];
+
+/// Types of values at well-known places of an event, used to build selectors like
+/// `$frame.vars.**`. A `*` matches any array index.
+static PATH_TYPES: &[(&'static str, &'static str)] = &[
+    ("exception.values.*", "$exception"),
+    ("exception.values.*.stacktrace", "$stacktrace"),
+    ("exception.values.*.stacktrace.frames.*", "$frame"),
+    ("threads.values.*", "$thread"),
+    ("threads.values.*.stacktrace", "$stacktrace"),
+    ("threads.values.*.stacktrace.frames.*", "$frame"),
+    ("stacktrace", "$stacktrace"),
+    ("stacktrace.frames.*", "$frame"),
+    ("breadcrumbs.values.*", "$breadcrumb"),
+    ("spans.*", "$span"),
+    ("logentry", "$logentry"),
+    ("request", "$request"),
+    ("user", "$user"),
+    ("sdk", "$sdk"),
+];
+
+#[derive(Clone, Copy, PartialEq)]
+enum CharClass {
+    Digit,
+    Lower,
+    Upper,
+    Alphanumeric,
+    Literal(char),
+}
+
+impl CharClass {
+    fn for_char(c: char, generalize: bool) -> CharClass {
+        if c.is_ascii_alphanumeric() && generalize {
+            CharClass::Alphanumeric
+        } else if c.is_ascii_digit() {
+            CharClass::Digit
+        } else if c.is_ascii_lowercase() {
+            CharClass::Lower
+        } else if c.is_ascii_uppercase() {
+            CharClass::Upper
+        } else {
+            CharClass::Literal(c)
+        }
+    }
+
+    fn to_regex(self) -> String {
+        match self {
+            CharClass::Digit => "[0-9]".to_owned(),
+            CharClass::Lower => "[a-z]".to_owned(),
+            CharClass::Upper => "[A-Z]".to_owned(),
+            CharClass::Alphanumeric => "[a-zA-Z0-9]".to_owned(),
+            CharClass::Literal(c) => regex::escape(&c.to_string()),
+        }
+    }
+}
+
+/// Builds a regex from the character classes of a value, collapsing runs of the same class
+/// into a repetition, e.g. `ab12` becomes `[a-z]{2}[0-9]{2}`.
+fn infer_pattern(value: &str, generalize: bool) -> String {
+    let mut runs: Vec<(CharClass, usize)> = vec![];
+
+    for c in value.chars() {
+        let class = CharClass::for_char(c, generalize);
+        match runs.last_mut() {
+            Some(&mut (last_class, ref mut count)) if last_class == class => *count += 1,
+            _ => runs.push((class, 1)),
+        }
+    }
+
+    runs.into_iter()
+        .map(|(class, count)| match count {
+            1 => class.to_regex(),
+            n => format!("{}{{{}}}", class.to_regex(), n),
+        })
+        .collect()
+}
+
+/// Returns regexes matching a value, from the most specific (the escaped literal) to the most
+/// general one.
+fn get_patterns_for_value(value: &str) -> Vec<String> {
+    let mut patterns = vec![regex::escape(value)];
+
+    for &generalize in &[false, true] {
+        let pattern = infer_pattern(value, generalize);
+        if !patterns.contains(&pattern) {
+            patterns.push(pattern);
+        }
+    }
+
+    patterns
+}
+
+fn get_redaction(method: &str) -> serde_json::Value {
+    match method {
+        "replace" => json!({ "method": "replace", "text": "[redacted]" }),
+        _ => json!({ "method": method }),
+    }
+}
+
+pub fn get_value_by_path<'a>(value: &'a Annotated<Value>, path: &str) -> Option<&'a Annotated<Value>> {
     if path.is_empty() || path == "." {
         Some(value)
     } else {
@@ -46,10 +149,14 @@ fn get_value_by_path<'a>(value: &'a Annotated<Value>, path: &str) -> Option<&'a
     }
 }
 
-trait PiiConfigExt: Sized {
+pub trait PiiConfigExt: Sized {
     fn add_rule(&mut self, pii_kind: &str, rule: &str) -> Result<bool, Error>;
     fn remove_rule(&mut self, pii_kind: &str, rule: &str) -> Result<bool, Error>;
+    fn create_rule(&mut self, rule: &str, spec: serde_json::Value) -> Result<bool, Error>;
+    #[cfg(feature = "web")]
+    fn delete_rule(&mut self, rule: &str) -> Result<bool, Error>;
     fn get_known_rules(&self) -> Vec<String>;
+    fn get_unused_rule_id(&self, prefix: &str) -> String;
 }
 
 impl PiiConfigExt for PiiConfig {
@@ -109,6 +216,32 @@ impl PiiConfigExt for PiiConfig {
         Ok(true)
     }
 
+    fn create_rule(&mut self, rule: &str, spec: serde_json::Value) -> Result<bool, Error> {
+        let rules = self
+            .0
+            .entry("rules")
+            .or_insert(json!({}))
+            .as_object_mut()
+            .ok_or_else(|| err_msg("Bad rules value"))?;
+
+        if rules.contains_key(rule) {
+            return Ok(false);
+        }
+
+        rules.insert(rule.to_string(), spec);
+        Ok(true)
+    }
+
+    #[cfg(feature = "web")]
+    fn delete_rule(&mut self, rule: &str) -> Result<bool, Error> {
+        let rules = match self.0.get_mut("rules") {
+            Some(x) => x.as_object_mut().ok_or_else(|| err_msg("Bad rules value"))?,
+            None => return Ok(false),
+        };
+
+        Ok(rules.remove(rule).is_some())
+    }
+
     fn get_known_rules(&self) -> Vec<String> {
         BUILTIN_RULES
             .iter()
@@ -124,43 +257,388 @@ impl PiiConfigExt for PiiConfig {
             .map(|x| x.to_string())
             .collect()
     }
+
+    fn get_unused_rule_id(&self, prefix: &str) -> String {
+        let known_rules = self.get_known_rules();
+        (1..)
+            .map(|i| format!("{}_{}", prefix, i))
+            .find(|rule| !known_rules.contains(rule))
+            .unwrap()
+    }
+}
+
+/// Returns the paths of all values in the event that a selector matches, by removing everything
+/// it matches and looking at the remarks left behind.
+pub fn get_selector_matches(event: &SensitiveEvent, selector: &str) -> Result<Vec<String>, Error> {
+    let mut config = PiiConfig(serde_json::Map::new());
+    config.create_rule("remove_anything", json!({
+        "type": "anything",
+        "redaction": { "method": "remove" },
+    }))?;
+    config.add_rule(selector, "remove_anything")?;
+
+    Ok(collect_remarks(&config.strip_event(event)?)
+        .into_iter()
+        .map(|(path, _)| path)
+        .collect())
+}
+
+fn get_value_type(value: &Value) -> &'static str {
+    match *value {
+        Value::String(_) => "$string",
+        Value::U64(_) | Value::I64(_) | Value::F64(_) => "$number",
+        Value::Bool(_) => "$boolean",
+        Value::Array(_) => "$array",
+        Value::Object(_) => "$object",
+    }
+}
+
+/// Returns whether the value or any value inside of it has the given type.
+fn contains_type(value: &Annotated<Value>, ty: &str) -> bool {
+    match value.value() {
+        Some(Value::Array(items)) => ty == "$array" || items.iter().any(|x| contains_type(x, ty)),
+        Some(Value::Object(items)) => ty == "$object" || items.values().any(|x| contains_type(x, ty)),
+        Some(value) => get_value_type(value) == ty,
+        None => false,
+    }
+}
+
+/// Returns whether a path segment can be used in a selector without quoting.
+fn is_plain_segment(segment: &str) -> bool {
+    !segment.is_empty() && segment.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Returns a path segment as it has to be written in a selector, quoting it if needed.
+pub fn quote_segment(segment: &str) -> String {
+    if is_plain_segment(segment) {
+        segment.to_owned()
+    } else {
+        // Quotes are escaped by doubling them in selectors.
+        format!("'{}'", segment.replace('\'', "''"))
+    }
+}
+
+fn path_matches_pattern(segments: &[&str], pattern: &str) -> bool {
+    let pattern: Vec<&str> = pattern.split('.').collect();
+    pattern.len() == segments.len()
+        && pattern
+            .iter()
+            .zip(segments)
+            .all(|(p, s)| *p == *s || (*p == "*" && usize::from_str(s).is_ok()))
+}
+
+/// Builds selectors between `$string` and the exact path of a value: parent wildcards, key names,
+/// well-known types combined with a path, and the value's type combined with its parent.
+fn get_intermediate_selectors(original: &Annotated<Value>, path: &str) -> Vec<String> {
+    let segments: Vec<&str> = path.split('.').filter(|x| !x.is_empty()).collect();
+    let quoted: Vec<String> = segments.iter().map(|x| quote_segment(x)).collect();
+    let mut rv = vec![];
+
+    if segments.is_empty() {
+        return rv;
+    }
+
+    let last = &quoted[quoted.len() - 1];
+    let is_key = usize::from_str(segments[segments.len() - 1]).is_err();
+
+    for k in 1..segments.len() {
+        let parent = quoted[..k].join(".");
+        if k == segments.len() - 1 {
+            rv.push(format!("{}.*", parent));
+        }
+        rv.push(format!("{}.**", parent));
+    }
+
+    if is_key {
+        rv.push(format!("**.{}", last));
+    }
+
+    for &(pattern, ty) in PATH_TYPES {
+        for k in 1..segments.len() {
+            if !path_matches_pattern(&segments[..k], pattern) {
+                continue;
+            }
+
+            let rest = &quoted[k..];
+            rv.push(format!("{}.{}", ty, rest.join(".")));
+            for j in 0..rest.len() - 1 {
+                rv.push(format!("{}.{}.**", ty, rest[..j + 1].join(".")));
+            }
+            rv.push(format!("{}.**", ty));
+        }
+    }
+
+    if let Some(Some(value)) = get_value_by_path(original, path).map(|x| x.value()) {
+        let value_type = get_value_type(value);
+        if segments.len() > 1 {
+            rv.push(format!("{} && {}.**", value_type, quoted[..quoted.len() - 1].join(".")));
+        }
+        if is_key {
+            rv.push(format!("{} && **.{}", value_type, last));
+        }
+    }
+
+    let mut seen = vec![];
+    rv.retain(|selector| {
+        if seen.contains(selector) {
+            false
+        } else {
+            seen.push(selector.clone());
+            true
+        }
+    });
+    rv
 }
 
-pub fn get_rule_suggestions_for_value(
+/// Returns the intermediate selectors that match the value at `path`, ordered by how many values
+/// of the event they match in total, narrowest first.
+fn get_covering_selectors(
     event: &SensitiveEvent,
-    old_config: &PiiConfig,
+    original: &Annotated<Value>,
     path: &str,
-) -> Result<Vec<PiiRuleSuggestion>, Error> {
+) -> Vec<String> {
+    let mut rv = vec![];
+
+    for selector in get_intermediate_selectors(original, path) {
+        let matches = match get_selector_matches(event, &selector) {
+            Ok(matches) => matches,
+            Err(_) => continue,
+        };
+
+        let covers_path = matches
+            .iter()
+            .any(|m| m == path || m.is_empty() || path.starts_with(&format!("{}.", m)));
+
+        if covers_path {
+            rv.push((matches.len(), selector));
+        }
+    }
+
+    rv.sort_by_key(|&(count, _)| count);
+    rv.into_iter().map(|(_, selector)| selector).collect()
+}
+
+/// Suggestions for changing a set of values of an event, as returned by
+/// `get_rule_suggestions_for_values`.
+#[derive(PartialEq, Serialize, Deserialize)]
+pub struct RuleSuggestions {
+    /// Single changes to the config that change all of the values.
+    pub suggestions: Vec<PiiRuleSuggestion>,
+    /// A combination of changes that jointly change all of the values. Only set if none of the
+    /// single suggestions does.
+    pub cover: Option<RuleCover>,
+    /// The number of suggestions that were discarded because they change pinned values.
+    pub rejected: usize,
+}
+
+/// Several suggestions applied on top of each other.
+#[derive(PartialEq, Serialize, Deserialize)]
+pub struct RuleCover {
+    pub suggestions: Vec<PiiRuleSuggestion>,
+    pub config: PiiConfig,
+    pub collateral: Vec<Change>,
+}
+
+/// A suggestion together with the indices of the selected values it changes.
+struct Candidate {
+    suggestion: PiiRuleSuggestion,
+    changed: Vec<usize>,
+    touches_pin: bool,
+}
+
+fn get_pattern_rule(pattern: &str, method: &str) -> serde_json::Value {
+    json!({
+        "type": "pattern",
+        "pattern": pattern,
+        "redaction": get_redaction(method),
+    })
+}
+
+/// Returns an event with nothing but the entry of `extra` or `contexts` that contains all paths, or
+/// `None` if the paths are anywhere else.
+fn get_subtree_event(original: &Annotated<Value>, paths: &[String]) -> Option<SensitiveEvent> {
+    let mut entry = None;
+
+    for path in paths {
+        let mut segments = path.splitn(3, '.');
+        let root = segments.next()?;
+        let key = segments.next()?;
+
+        if root != "extra" && root != "contexts" {
+            return None;
+        }
+
+        if *entry.get_or_insert((root, key)) != (root, key) {
+            return None;
+        }
+    }
+
+    let (root, key) = entry?;
+    let value = get_value_by_path(original, &format!("{}.{}", root, key))?;
+
+    let mut entries = Object::new();
+    entries.insert(key.to_owned(), value.clone());
+    let mut event = Object::new();
+    event.insert(root.to_owned(), Annotated::new(Value::Object(entries)));
+
+    let json = Annotated::new(Value::Object(event)).to_json().ok()?;
+    SensitiveEvent::from_json(&json).ok()
+}
+
+/// Returns suggestions that change all values at `paths`.
+///
+/// Values at `pins` must be kept. Suggestions that change them are discarded, unless they only
+/// restore a pinned value to what it was in the original event.
+pub fn get_rule_suggestions_for_values(
+    event: &SensitiveEvent,
+    old_config: &PiiConfig,
+    paths: &[String],
+    pins: &[String],
+) -> Result<RuleSuggestions, Error> {
+    let original = to_value_tree(event.clone());
     let old_result = old_config.strip_event(event)?;
-    let old_value = get_value_by_path(&old_result, path).map(|x| x.value());
+    let old_values = paths
+        .iter()
+        .map(|path| get_value_by_path(&old_result, path).map(|x| x.value()))
+        .collect::<Vec<_>>();
 
-    let mut rv = vec![];
+    // Selecting a pinned value overrides the pin.
+    let pinned = pins
+        .iter()
+        .filter(|pin| !paths.contains(*pin))
+        .map(|pin| {
+            let old_value = get_value_by_path(&old_result, pin).map(|x| x.value());
+            let original_value = get_value_by_path(&original, pin).map(|x| x.value());
+            (pin, old_value, original_value)
+        })
+        .collect::<Vec<_>>();
+
+    // Values in `extra` and `contexts` are matched by the same selectors no matter what else is in
+    // the event, so a candidate can be checked on an event with only their entry first.
+    let subtree = get_subtree_event(&original, paths);
+    let subtree_old_result = match subtree {
+        Some(ref subtree) => Some(old_config.strip_event(subtree)?),
+        None => None,
+    };
+    let subtree_old_values = subtree_old_result.as_ref().map(|result| {
+        paths
+            .iter()
+            .map(|path| get_value_by_path(result, path).map(|x| x.value()))
+            .collect::<Vec<_>>()
+    });
 
-    println!("Old value: {:?}", old_value);
+    let mut candidates = vec![];
 
+    // Returns the indices of the selected values that the new config changes, all other values it
+    // changes and whether it changes a pinned value, or `None` if it leaves all selected values as
+    // they are.
     let rule_does_something = |new_config: &PiiConfig| {
+        // Most candidates change none of the selected values, which the subtree tells much faster.
+        if let (Some(subtree), Some(old_values)) = (subtree.as_ref(), subtree_old_values.as_ref()) {
+            let new_result = match new_config.strip_event(subtree) {
+                Ok(x) => x,
+                Err(_) => return None,
+            };
+
+            let unchanged = paths
+                .iter()
+                .zip(old_values)
+                .all(|(path, old_value)| get_value_by_path(&new_result, path).map(|x| x.value()) == *old_value);
+
+            if unchanged {
+                return None;
+            }
+        }
+
         let new_result = match new_config.strip_event(event) {
             Ok(x) => x,
-            Err(_) => return false,
+            Err(_) => return None,
         };
 
-        let new_value = get_value_by_path(&new_result, path).map(|x| x.value());
+        let changed = paths
+            .iter()
+            .zip(&old_values)
+            .enumerate()
+            .filter(|&(_, (path, old_value))| {
+                get_value_by_path(&new_result, path).map(|x| x.value()) != *old_value
+            })
+            .map(|(index, _)| index)
+            .collect::<Vec<_>>();
+
+        if changed.is_empty() {
+            return None;
+        }
+
+        let collateral = diff_values(&old_result, &new_result)
+            .into_iter()
+            .filter(|change| !paths.iter().any(|path| is_related_path(&change.path, path)))
+            .collect::<Vec<_>>();
 
-        new_value != old_value
+        let touches_pin = pinned.iter().any(|&(pin, ref old_value, ref original_value)| {
+            let new_value = get_value_by_path(&new_result, pin).map(|x| x.value());
+            new_value != *old_value && new_value != *original_value
+        });
+
+        Some((changed, collateral, touches_pin))
     };
 
-    for pii_kind in PII_KINDS.iter().chain(&[path]) {
-        let known_rules = old_config.get_known_rules();
+    // Try the exact paths first, then narrow selectors, then the broad PII kinds.
+    let mut pii_kinds = paths
+        .iter()
+        .map(|path| path.split('.').map(quote_segment).collect::<Vec<_>>().join("."))
+        .collect::<Vec<_>>();
+    for path in paths {
+        for selector in get_covering_selectors(event, &original, path) {
+            if !pii_kinds.contains(&selector) {
+                pii_kinds.push(selector);
+            }
+        }
+    }
+
+    // A selector can only change the selected values if it matches them, one of their parents or
+    // one of their children. Checking that once per PII kind is much cheaper than stripping the
+    // event for every rule on it.
+    let selected = paths
+        .iter()
+        .filter_map(|path| get_value_by_path(&original, path))
+        .collect::<Vec<_>>();
 
+    for &pii_kind in PII_KINDS {
+        if SCALAR_KINDS.contains(&pii_kind) && !selected.iter().any(|value| contains_type(value, pii_kind)) {
+            continue;
+        }
+
+        let matches = match get_selector_matches(event, pii_kind) {
+            Ok(matches) => matches,
+            Err(_) => continue,
+        };
+
+        let matches_selected = matches
+            .iter()
+            .any(|m| paths.iter().any(|path| is_related_path(m, path)));
+
+        if matches_selected && !pii_kinds.iter().any(|x| x == pii_kind) {
+            pii_kinds.push(pii_kind.to_owned());
+        }
+    }
+
+    let known_rules = old_config.get_known_rules();
+
+    for pii_kind in &pii_kinds {
         for rule in &known_rules {
             let mut new_config = old_config.clone();
             if new_config.add_rule(pii_kind, &rule)? {
                 // Adding a rule for the value
-                if rule_does_something(&new_config) {
-                    rv.push(PiiRuleSuggestion::ActivateRule {
-                        pii_kind: pii_kind.to_string(),
-                        rule: (*rule).to_owned(),
-                        config: new_config,
+                if let Some((changed, collateral, touches_pin)) = rule_does_something(&new_config) {
+                    candidates.push(Candidate {
+                        suggestion: PiiRuleSuggestion::ActivateRule {
+                            pii_kind: pii_kind.to_string(),
+                            rule: (*rule).to_owned(),
+                            config: new_config,
+                            collateral,
+                        },
+                        changed,
+                        touches_pin,
                     });
                 }
             }
@@ -168,31 +646,222 @@ pub fn get_rule_suggestions_for_value(
             let mut new_config = old_config.clone();
             if new_config.remove_rule(pii_kind, &rule)? {
                 // Removing a rule for the value
-                if rule_does_something(&new_config) {
-                    rv.push(PiiRuleSuggestion::DeactivateRule {
-                        pii_kind: pii_kind.to_string(),
-                        rule: (*rule).to_owned(),
-                        config: new_config,
+                if let Some((changed, collateral, touches_pin)) = rule_does_something(&new_config) {
+                    candidates.push(Candidate {
+                        suggestion: PiiRuleSuggestion::DeactivateRule {
+                            pii_kind: pii_kind.to_string(),
+                            rule: (*rule).to_owned(),
+                            config: new_config,
+                            collateral,
+                        },
+                        changed,
+                        touches_pin,
                     });
                 }
             }
         }
     }
 
+    let rule = old_config.get_unused_rule_id("custom_rule");
+    let mut patterns: Vec<String> = vec![];
+
+    for path in paths {
+        if let Some(Some(Value::String(string))) = get_value_by_path(&original, path).map(|x| x.value()) {
+            for pattern in get_patterns_for_value(string) {
+                if !patterns.contains(&pattern) {
+                    patterns.push(pattern);
+                }
+            }
+        }
+    }
+
+    for pattern in &patterns {
+        // The default method depends on the rule, which is not helpful for a new one.
+        for method in REDACTION_METHODS.iter().filter(|method| **method != "default") {
+            let mut new_config = old_config.clone();
+
+            if new_config.create_rule(&rule, get_pattern_rule(pattern, method))?
+                && new_config.add_rule("$string", &rule)?
+            {
+                // Creating a new rule for the value
+                if let Some((changed, collateral, touches_pin)) = rule_does_something(&new_config) {
+                    candidates.push(Candidate {
+                        suggestion: PiiRuleSuggestion::CreateRule {
+                            pii_kind: "$string".to_owned(),
+                            rule: rule.clone(),
+                            pattern: pattern.clone(),
+                            method: method.to_string(),
+                            config: new_config,
+                            collateral,
+                        },
+                        changed,
+                        touches_pin,
+                    });
+                }
+            }
+        }
+    }
+
+    // Least destructive first. The sort is stable, so ties keep the order from above.
+    candidates.sort_by_key(|candidate| candidate.suggestion.collateral().len());
+
+    let rejected = candidates.iter().filter(|candidate| candidate.touches_pin).count();
+    candidates.retain(|candidate| !candidate.touches_pin);
+
+    let (complete, partial): (Vec<_>, Vec<_>) = candidates
+        .into_iter()
+        .partition(|candidate| candidate.changed.len() == paths.len());
+
+    let cover = if complete.is_empty() && paths.len() > 1 {
+        get_rule_cover(old_config, partial, paths.len())?.and_then(|(suggestions, config)| {
+            rule_does_something(&config)
+                .filter(|(changed, _, touches_pin)| changed.len() == paths.len() && !touches_pin)
+                .map(|(_, collateral, _)| RuleCover {
+                    suggestions,
+                    config,
+                    collateral,
+                })
+        })
+    } else {
+        None
+    };
+
+    Ok(RuleSuggestions {
+        suggestions: complete.into_iter().map(|candidate| candidate.suggestion).collect(),
+        cover,
+        rejected,
+    })
+}
+
+/// Picks suggestions until all `count` values are changed, each time taking the one that changes
+/// the most remaining values. This is the usual greedy approximation of the smallest set cover.
+///
+/// Returns the picked suggestions and the config with all of them applied, or `None` if the
+/// suggestions cannot change all values even together.
+fn get_rule_cover(
+    old_config: &PiiConfig,
+    mut candidates: Vec<Candidate>,
+    count: usize,
+) -> Result<Option<(Vec<PiiRuleSuggestion>, PiiConfig)>, Error> {
+    let mut uncovered = (0..count).collect::<Vec<_>>();
+    let mut config = old_config.clone();
+    let mut rv = vec![];
+
+    while !uncovered.is_empty() {
+        // `max_by_key` returns the last maximum, so iterate in reverse to prefer the candidates
+        // with the least collateral.
+        let best = candidates
+            .iter()
+            .enumerate()
+            .rev()
+            .map(|(index, candidate)| {
+                let covered = candidate.changed.iter().filter(|x| uncovered.contains(*x)).count();
+                (index, covered)
+            })
+            .max_by_key(|&(_, covered)| covered);
+
+        let index = match best {
+            Some((index, covered)) if covered > 0 => index,
+            _ => return Ok(None),
+        };
+
+        let candidate = candidates.remove(index);
+        uncovered.retain(|x| !candidate.changed.contains(x));
+        candidate.suggestion.apply(&mut config)?;
+        rv.push(candidate.suggestion);
+    }
+
+    Ok(Some((rv, config)))
+}
+
+/// Whether `other` is the same value as `path`, or one of its parents or children.
+fn is_related_path(other: &str, path: &str) -> bool {
+    fn is_prefix(prefix: &str, path: &str) -> bool {
+        prefix.is_empty()
+            || path == prefix
+            || (path.starts_with(prefix) && path[prefix.len()..].starts_with('.'))
+    }
+
+    is_prefix(other, path) || is_prefix(path, other)
+}
+
+/// An entry in `applications` that can be removed without changing any of the stripped events.
+#[cfg(feature = "web")]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct IneffectiveRule {
+    pub selector: String,
+    pub rule: String,
+    /// The config without this entry.
+    pub config: PiiConfig,
+}
+
+/// Applies the config with and without each of its `applications` entries to all events, and
+/// returns the entries whose removal changes nothing.
+#[cfg(feature = "web")]
+pub fn get_ineffective_rules(
+    events: &[SensitiveEvent],
+    compiled: &CompiledPiiConfig,
+) -> Result<Vec<IneffectiveRule>, Error> {
+    let old_results = events
+        .iter()
+        .map(|event| compiled.strip_event(event))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut rv = vec![];
+
+    for (selector, rule) in compiled.config.get_applications() {
+        let mut new_config = compiled.config.clone();
+        if !new_config.remove_rule(&selector, &rule)? {
+            continue;
+        }
+
+        let compiled = match new_config.compile() {
+            Ok(compiled) => compiled,
+            Err(_) => continue,
+        };
+
+        let changes_nothing = events.iter().zip(&old_results).all(|(event, old_result)| {
+            match compiled.strip_event(event) {
+                Ok(new_result) => new_result == *old_result,
+                Err(_) => false,
+            }
+        });
+
+        if changes_nothing {
+            rv.push(IneffectiveRule {
+                selector,
+                rule,
+                config: new_config,
+            });
+        }
+    }
+
     Ok(rv)
 }
 
-#[derive(PartialEq)]
+#[derive(PartialEq, Serialize, Deserialize)]
 pub enum PiiRuleSuggestion {
     ActivateRule {
         pii_kind: String,
         rule: String,
         config: PiiConfig,
+        /// Other values in the event that this suggestion changes as well.
+        collateral: Vec<Change>,
     },
     DeactivateRule {
         pii_kind: String,
         rule: String,
         config: PiiConfig,
+        /// Other values in the event that this suggestion changes as well.
+        collateral: Vec<Change>,
+    },
+    CreateRule {
+        pii_kind: String,
+        rule: String,
+        pattern: String,
+        method: String,
+        config: PiiConfig,
+        collateral: Vec<Change>,
     },
 }
 
@@ -201,6 +870,476 @@ impl PiiRuleSuggestion {
         match *self {
             PiiRuleSuggestion::ActivateRule { ref pii_kind, .. } => pii_kind,
             PiiRuleSuggestion::DeactivateRule { ref pii_kind, .. } => pii_kind,
+            PiiRuleSuggestion::CreateRule { ref pii_kind, .. } => pii_kind,
+        }
+    }
+
+    pub fn collateral(&self) -> &[Change] {
+        match *self {
+            PiiRuleSuggestion::ActivateRule { ref collateral, .. } => collateral,
+            PiiRuleSuggestion::DeactivateRule { ref collateral, .. } => collateral,
+            PiiRuleSuggestion::CreateRule { ref collateral, .. } => collateral,
+        }
+    }
+
+    /// Makes the same change to another config. New rules get an id that is unused there.
+    pub fn apply(&self, config: &mut PiiConfig) -> Result<(), Error> {
+        match *self {
+            PiiRuleSuggestion::ActivateRule {
+                ref pii_kind,
+                ref rule,
+                ..
+            } => {
+                config.add_rule(pii_kind, rule)?;
+            }
+            PiiRuleSuggestion::DeactivateRule {
+                ref pii_kind,
+                ref rule,
+                ..
+            } => {
+                config.remove_rule(pii_kind, rule)?;
+            }
+            PiiRuleSuggestion::CreateRule {
+                ref pii_kind,
+                ref pattern,
+                ref method,
+                ..
+            } => {
+                let rule = config.get_unused_rule_id("custom_rule");
+                config.create_rule(&rule, get_pattern_rule(pattern, method))?;
+                config.add_rule(pii_kind, &rule)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Instant;
+
+    use super::*;
+
+    fn get_event(keys: usize) -> SensitiveEvent {
+        let mut extra = serde_json::Map::new();
+        for i in 0..keys {
+            extra.insert(
+                format!("key_{}", i),
+                json!({ "ip": "127.0.0.1", "count": i, "flag": true, "name": format!("value {}", i) }),
+            );
+        }
+
+        SensitiveEvent::from_json(&json!({ "level": "error", "extra": extra }).to_string()).unwrap()
+    }
+
+    fn get_config() -> PiiConfig {
+        PiiConfig::from_value(json!({ "applications": { "$string": ["@ip:replace"] } })).unwrap()
+    }
+
+    #[test]
+    fn test_infer_pattern() {
+        assert_eq!(infer_pattern("ab12", false), "[a-z]{2}[0-9]{2}");
+        assert_eq!(infer_pattern("ab12", true), "[a-zA-Z0-9]{4}");
+        assert_eq!(infer_pattern("Foo.42", false), "[A-Z][a-z]{2}\\.[0-9]{2}");
+        assert_eq!(infer_pattern("Foo.42", true), "[a-zA-Z0-9]{3}\\.[a-zA-Z0-9]{2}");
+        assert_eq!(infer_pattern("", false), "");
+    }
+
+    #[test]
+    fn test_get_patterns_for_value() {
+        assert_eq!(
+            get_patterns_for_value("ab12"),
+            vec!["ab12", "[a-z]{2}[0-9]{2}", "[a-zA-Z0-9]{4}"]
+        );
+
+        // Without alphanumeric characters, both inferred patterns are the same.
+        assert_eq!(get_patterns_for_value(".."), vec!["\\.\\.", "\\.{2}"]);
+
+        for value in &["ab12", "Foo.42", "user@example.com", "a+b (c)"] {
+            for pattern in get_patterns_for_value(value) {
+                let regex = regex::Regex::new(&format!("^{}$", pattern)).unwrap();
+                assert!(regex.is_match(value), "{} does not match {}", pattern, value);
+            }
+        }
+    }
+
+    #[test]
+    fn test_get_intermediate_selectors_frame_vars() {
+        let event = SensitiveEvent::from_json(
+            &json!({
+                "exception": {
+                    "values": [{ "stacktrace": { "frames": [{ "vars": { "foo": "bar" } }] } }]
+                }
+            })
+            .to_string(),
+        )
+        .unwrap();
+
+        let path = "exception.values.0.stacktrace.frames.0.vars.foo";
+        assert_eq!(
+            get_intermediate_selectors(&to_value_tree(event), path),
+            vec![
+                "exception.**",
+                "exception.values.**",
+                "exception.values.0.**",
+                "exception.values.0.stacktrace.**",
+                "exception.values.0.stacktrace.frames.**",
+                "exception.values.0.stacktrace.frames.0.**",
+                "exception.values.0.stacktrace.frames.0.vars.*",
+                "exception.values.0.stacktrace.frames.0.vars.**",
+                "**.foo",
+                "$exception.stacktrace.frames.0.vars.foo",
+                "$exception.stacktrace.**",
+                "$exception.stacktrace.frames.**",
+                "$exception.stacktrace.frames.0.**",
+                "$exception.stacktrace.frames.0.vars.**",
+                "$exception.**",
+                "$stacktrace.frames.0.vars.foo",
+                "$stacktrace.frames.**",
+                "$stacktrace.frames.0.**",
+                "$stacktrace.frames.0.vars.**",
+                "$stacktrace.**",
+                "$frame.vars.foo",
+                "$frame.vars.**",
+                "$frame.**",
+                "$string && exception.values.0.stacktrace.frames.0.vars.**",
+                "$string && **.foo",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_get_intermediate_selectors_extra() {
+        let event = SensitiveEvent::from_json(r#"{"extra": {"tenant-id": "abc"}}"#).unwrap();
+
+        assert_eq!(
+            get_intermediate_selectors(&to_value_tree(event), "extra.tenant-id"),
+            vec![
+                "extra.*",
+                "extra.**",
+                "**.'tenant-id'",
+                "$string && extra.**",
+                "$string && **.'tenant-id'",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_get_covering_selectors() {
+        let event = SensitiveEvent::from_json(r#"{"extra": {"foo": "abc", "bar": "xyz", "count": 3}}"#).unwrap();
+        let original = to_value_tree(event.clone());
+
+        // Ordered by the number of values each selector matches: one, two strings, and all three.
+        assert_eq!(
+            get_covering_selectors(&event, &original, "extra.foo"),
+            vec!["**.foo", "$string && **.foo", "$string && extra.**", "extra.*", "extra.**"]
+        );
+    }
+
+    fn get_candidate(pii_kind: &str, changed: Vec<usize>) -> Candidate {
+        let mut config = get_config();
+        config.add_rule(pii_kind, "@ip:replace").unwrap();
+
+        Candidate {
+            suggestion: PiiRuleSuggestion::ActivateRule {
+                pii_kind: pii_kind.to_owned(),
+                rule: "@ip:replace".to_owned(),
+                config,
+                collateral: vec![],
+            },
+            changed,
+            touches_pin: false,
+        }
+    }
+
+    #[test]
+    fn test_get_rule_cover() {
+        let candidates = vec![
+            get_candidate("extra.a", vec![0]),
+            get_candidate("extra.b", vec![0, 1]),
+            get_candidate("extra.c", vec![2]),
+            get_candidate("extra.d", vec![1, 2]),
+        ];
+
+        // `extra.b` and `extra.d` both change two values, and of the remaining ones `extra.c` and
+        // `extra.d` both change the last. Ties go to the candidate that comes first.
+        let (suggestions, config) = get_rule_cover(&get_config(), candidates, 3).unwrap().unwrap();
+        let pii_kinds: Vec<_> = suggestions.iter().map(|x| x.pii_kind()).collect();
+        assert_eq!(pii_kinds, vec!["extra.b", "extra.c"]);
+
+        assert_eq!(
+            config.get_applications(),
+            vec![
+                ("$string".to_owned(), "@ip:replace".to_owned()),
+                ("extra.b".to_owned(), "@ip:replace".to_owned()),
+                ("extra.c".to_owned(), "@ip:replace".to_owned()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_get_rule_cover_incomplete() {
+        let candidates = vec![get_candidate("extra.a", vec![0]), get_candidate("extra.b", vec![0, 1])];
+        assert!(get_rule_cover(&get_config(), candidates, 3).unwrap().is_none());
+        assert!(get_rule_cover(&get_config(), vec![], 1).unwrap().is_none());
+    }
+
+    /// The search as it was before PII kinds were pruned and candidates were checked on a subtree
+    /// first, kept to check and measure the current search against.
+    fn get_unpruned_suggestions(
+        event: &SensitiveEvent,
+        old_config: &PiiConfig,
+        paths: &[String],
+        pins: &[String],
+    ) -> Result<RuleSuggestions, Error> {
+        let original = Annotated::<Value>::from_json(&event.to_json()?)?;
+        let old_result = old_config.strip_event(event)?;
+        let old_values = paths
+            .iter()
+            .map(|path| get_value_by_path(&old_result, path).map(|x| x.value()))
+            .collect::<Vec<_>>();
+
+        // Selecting a pinned value overrides the pin.
+        let pinned = pins
+            .iter()
+            .filter(|pin| !paths.contains(*pin))
+            .map(|pin| {
+                let old_value = get_value_by_path(&old_result, pin).map(|x| x.value());
+                let original_value = get_value_by_path(&original, pin).map(|x| x.value());
+                (pin, old_value, original_value)
+            })
+            .collect::<Vec<_>>();
+
+        let mut candidates = vec![];
+
+        // Returns the indices of the selected values that the new config changes, all other values it
+        // changes and whether it changes a pinned value, or `None` if it leaves all selected values as
+        // they are.
+        let rule_does_something = |new_config: &PiiConfig| {
+            let new_result = match new_config.strip_event(event) {
+                Ok(x) => x,
+                Err(_) => return None,
+            };
+
+            let changed = paths
+                .iter()
+                .zip(&old_values)
+                .enumerate()
+                .filter(|&(_, (path, old_value))| {
+                    get_value_by_path(&new_result, path).map(|x| x.value()) != *old_value
+                })
+                .map(|(index, _)| index)
+                .collect::<Vec<_>>();
+
+            if changed.is_empty() {
+                return None;
+            }
+
+            let collateral = diff_values(&old_result, &new_result)
+                .into_iter()
+                .filter(|change| !paths.iter().any(|path| is_related_path(&change.path, path)))
+                .collect::<Vec<_>>();
+
+            let touches_pin = pinned.iter().any(|&(pin, ref old_value, ref original_value)| {
+                let new_value = get_value_by_path(&new_result, pin).map(|x| x.value());
+                new_value != *old_value && new_value != *original_value
+            });
+
+            Some((changed, collateral, touches_pin))
+        };
+
+        // Try the exact paths first, then narrow selectors, then the broad PII kinds.
+        let mut pii_kinds = paths.to_vec();
+        for path in paths {
+            for selector in get_covering_selectors(event, &original, path) {
+                if !pii_kinds.contains(&selector) {
+                    pii_kinds.push(selector);
+                }
+            }
+        }
+        pii_kinds.extend(PII_KINDS.iter().map(|x| x.to_string()));
+
+        for pii_kind in &pii_kinds {
+            let known_rules = old_config.get_known_rules();
+
+            for rule in &known_rules {
+                let mut new_config = old_config.clone();
+                if new_config.add_rule(pii_kind, &rule)? {
+                    // Adding a rule for the value
+                    if let Some((changed, collateral, touches_pin)) = rule_does_something(&new_config) {
+                        candidates.push(Candidate {
+                            suggestion: PiiRuleSuggestion::ActivateRule {
+                                pii_kind: pii_kind.to_string(),
+                                rule: (*rule).to_owned(),
+                                config: new_config,
+                                collateral,
+                            },
+                            changed,
+                            touches_pin,
+                        });
+                    }
+                }
+
+                let mut new_config = old_config.clone();
+                if new_config.remove_rule(pii_kind, &rule)? {
+                    // Removing a rule for the value
+                    if let Some((changed, collateral, touches_pin)) = rule_does_something(&new_config) {
+                        candidates.push(Candidate {
+                            suggestion: PiiRuleSuggestion::DeactivateRule {
+                                pii_kind: pii_kind.to_string(),
+                                rule: (*rule).to_owned(),
+                                config: new_config,
+                                collateral,
+                            },
+                            changed,
+                            touches_pin,
+                        });
+                    }
+                }
+            }
+        }
+
+        let rule = old_config.get_unused_rule_id("custom_rule");
+        let mut patterns: Vec<String> = vec![];
+
+        for path in paths {
+            if let Some(Some(Value::String(string))) = get_value_by_path(&original, path).map(|x| x.value()) {
+                for pattern in get_patterns_for_value(string) {
+                    if !patterns.contains(&pattern) {
+                        patterns.push(pattern);
+                    }
+                }
+            }
+        }
+
+        for pattern in &patterns {
+            for method in REDACTION_METHODS.iter().filter(|method| **method != "default") {
+                let mut new_config = old_config.clone();
+
+                if new_config.create_rule(&rule, get_pattern_rule(pattern, method))?
+                    && new_config.add_rule("$string", &rule)?
+                {
+                    // Creating a new rule for the value
+                    if let Some((changed, collateral, touches_pin)) = rule_does_something(&new_config) {
+                        candidates.push(Candidate {
+                            suggestion: PiiRuleSuggestion::CreateRule {
+                                pii_kind: "$string".to_owned(),
+                                rule: rule.clone(),
+                                pattern: pattern.clone(),
+                                method: method.to_string(),
+                                config: new_config,
+                                collateral,
+                            },
+                            changed,
+                            touches_pin,
+                        });
+                    }
+                }
+            }
+        }
+
+        // Least destructive first. The sort is stable, so ties keep the order from above.
+        candidates.sort_by_key(|candidate| candidate.suggestion.collateral().len());
+
+        let rejected = candidates.iter().filter(|candidate| candidate.touches_pin).count();
+        candidates.retain(|candidate| !candidate.touches_pin);
+
+        let (complete, partial): (Vec<_>, Vec<_>) = candidates
+            .into_iter()
+            .partition(|candidate| candidate.changed.len() == paths.len());
+
+        let cover = if complete.is_empty() && paths.len() > 1 {
+            get_rule_cover(old_config, partial, paths.len())?.and_then(|(suggestions, config)| {
+                rule_does_something(&config)
+                    .filter(|(changed, _, touches_pin)| changed.len() == paths.len() && !touches_pin)
+                    .map(|(_, collateral, _)| RuleCover {
+                        suggestions,
+                        config,
+                        collateral,
+                    })
+            })
+        } else {
+            None
+        };
+
+        Ok(RuleSuggestions {
+            suggestions: complete.into_iter().map(|candidate| candidate.suggestion).collect(),
+            cover,
+            rejected,
+        })
+    }
+
+    /// Returns the rules that suggestions add or remove on a PII kind, for comparing the current
+    /// search with the unpruned one.
+    fn get_suggestions(event: &SensitiveEvent, config: &PiiConfig, path: &str, unpruned: bool) -> Vec<(String, String, bool)> {
+        let paths = [path.to_owned()];
+        let suggestions = if !unpruned {
+            get_rule_suggestions_for_values(event, config, &paths, &[]).unwrap()
+        } else {
+            get_unpruned_suggestions(event, config, &paths, &[]).unwrap()
+        };
+
+        let mut rv = suggestions
+            .suggestions
+            .iter()
+            .filter(|suggestion| PII_KINDS.iter().any(|x| *x == suggestion.pii_kind()))
+            .filter_map(|suggestion| match *suggestion {
+                PiiRuleSuggestion::ActivateRule {
+                    ref pii_kind, ref rule, ..
+                } => Some((pii_kind.clone(), rule.clone(), true)),
+                PiiRuleSuggestion::DeactivateRule {
+                    ref pii_kind, ref rule, ..
+                } => Some((pii_kind.clone(), rule.clone(), false)),
+                PiiRuleSuggestion::CreateRule { .. } => None,
+            })
+            .collect::<Vec<_>>();
+
+        rv.sort();
+        rv.dedup();
+        rv
+    }
+
+    #[test]
+    fn test_pruning_keeps_suggestions() {
+        let event = get_event(5);
+        let config = get_config();
+
+        for path in &["extra.key_1.ip", "extra.key_1.count", "extra.key_1"] {
+            assert_eq!(
+                get_suggestions(&event, &config, path, false),
+                get_suggestions(&event, &config, path, true),
+                "suggestions for {}",
+                path
+            );
+        }
+    }
+
+    /// Run with `cargo test --release --no-default-features --features cli -- --ignored --nocapture`.
+    #[test]
+    #[ignore]
+    fn bench_suggestions() {
+        let event = get_event(200);
+        let config = get_config();
+
+        for path in &["extra.key_1.ip", "extra.key_1.count"] {
+            let paths = [path.to_string()];
+
+            let start = Instant::now();
+            get_unpruned_suggestions(&event, &config, &paths, &[]).unwrap();
+            let unpruned = start.elapsed();
+
+            let start = Instant::now();
+            get_rule_suggestions_for_values(&event, &config, &paths, &[]).unwrap();
+            let pruned = start.elapsed();
+
+            println!(
+                "{}: {:?} without pruning, {:?} with pruning ({:.1}x)",
+                path,
+                unpruned,
+                pruned,
+                unpruned.as_secs_f64() / pruned.as_secs_f64()
+            );
         }
     }
 }
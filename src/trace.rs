@@ -0,0 +1,126 @@
+use failure::Error;
+use relay_general::pii::BUILTIN_RULES_MAP;
+use relay_general::types::{Remark, RemarkType};
+use serde_json;
+
+use remarks::collect_remarks;
+use suggestions::PiiConfigExt;
+use types::{get_rule_references, PiiConfig, SensitiveEvent};
+
+/// Explains which entry of a PII config caused a redaction.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct RedactionTrace {
+    /// The selector in `applications` that matched the value.
+    pub selector: String,
+    /// The rules from the one listed in `applications` down to the one that redacted the value.
+    pub rules: Vec<String>,
+    /// The redaction method of the last rule.
+    pub method: String,
+}
+
+fn get_remarks_at(config: &PiiConfig, event: &SensitiveEvent, path: &str) -> Result<Vec<Remark>, Error> {
+    Ok(collect_remarks(&config.strip_event(event)?)
+        .into_iter()
+        .filter(|(remark_path, _)| remark_path == path)
+        .map(|(_, remark)| remark)
+        .collect())
+}
+
+/// Returns the definition of a rule from the config, or from Relay's builtin rules.
+fn get_rule_spec(config: &PiiConfig, rule: &str) -> Option<serde_json::Value> {
+    config.get_rule(rule).cloned().or_else(|| {
+        BUILTIN_RULES_MAP
+            .get(rule)
+            .and_then(|spec| serde_json::to_value(spec).ok())
+    })
+}
+
+/// Finds the chain of rules from `rule` to `leaf`, following aliases and `multiple` rules.
+fn resolve_rule_chain(config: &PiiConfig, rule: &str, leaf: &str, seen: &mut Vec<String>) -> Option<Vec<String>> {
+    if rule == leaf {
+        return Some(vec![rule.to_owned()]);
+    }
+
+    if seen.iter().any(|x| x == rule) {
+        return None;
+    }
+    seen.push(rule.to_owned());
+
+    let spec = get_rule_spec(config, rule);
+    for reference in spec.as_ref().map(get_rule_references).unwrap_or_default() {
+        if let Some(mut chain) = resolve_rule_chain(config, reference, leaf, seen) {
+            chain.insert(0, rule.to_owned());
+            return Some(chain);
+        }
+    }
+
+    None
+}
+
+fn get_redaction_method(config: &PiiConfig, remark: &Remark) -> String {
+    let spec = get_rule_spec(config, remark.rule_id());
+    let method = spec
+        .as_ref()
+        .and_then(|rule| rule.get("redaction"))
+        .and_then(|redaction| redaction.get("method"))
+        .and_then(|method| method.as_str());
+
+    if let Some(method) = method {
+        return method.to_owned();
+    }
+
+    match remark.ty() {
+        RemarkType::Removed => "remove",
+        RemarkType::Substituted => "replace",
+        RemarkType::Masked => "mask",
+        RemarkType::Pseudonymized => "hash",
+        RemarkType::Encrypted => "encrypt",
+        RemarkType::Annotated => "annotate",
+    }
+    .to_owned()
+}
+
+/// Explains the redactions of the value at `path`. Every entry in `applications` is removed in
+/// turn, and an entry is reported if that makes one of the remarks at `path` go away.
+pub fn trace_redactions(
+    event: &SensitiveEvent,
+    config: &PiiConfig,
+    path: &str,
+) -> Result<Vec<RedactionTrace>, Error> {
+    let remarks = get_remarks_at(config, event, path)?;
+    let mut rv = vec![];
+
+    if remarks.is_empty() {
+        return Ok(rv);
+    }
+
+    for (selector, rule) in config.get_applications() {
+        let mut new_config = config.clone();
+        if !new_config.remove_rule(&selector, &rule)? {
+            continue;
+        }
+
+        let new_remarks = get_remarks_at(&new_config, event, path)?;
+
+        for remark in &remarks {
+            if new_remarks.contains(remark) {
+                continue;
+            }
+
+            let leaf = remark.rule_id();
+            let rules = resolve_rule_chain(config, &rule, leaf, &mut vec![]).unwrap_or_else(|| {
+                // The remark names a rule that is not reachable from the config, so only show
+                // both ends.
+                vec![rule.clone(), leaf.to_owned()]
+            });
+
+            rv.push(RedactionTrace {
+                selector: selector.clone(),
+                rules,
+                method: get_redaction_method(config, remark),
+            });
+        }
+    }
+
+    Ok(rv)
+}
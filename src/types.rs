@@ -1,16 +1,30 @@
 use relay_general::pii::{PiiConfig as ProcessorPiiConfig, PiiProcessor};
 use relay_general::processor::process_value;
 use relay_general::protocol::Event;
-use relay_general::types::{Annotated, Value};
+use relay_general::types::{Annotated, ToValue, Value};
+
+#[cfg(feature = "web")]
+use relay_general::processor::{ProcessingState, Processor};
+#[cfg(feature = "web")]
+use relay_general::store::{StoreConfig, StoreProcessor};
+#[cfg(feature = "web")]
+use relay_general::types::{Object, ProcessingResult};
 
 use failure::{Error, ResultExt};
 
+#[cfg(any(feature = "web", test))]
+use failure::err_msg;
+
 use serde_json;
 
 pub type SensitiveEvent = Annotated<Event>;
 pub type StrippedEvent = Annotated<Value>;
 
-#[derive(Clone, Debug, PartialEq)]
+/// The redaction methods of PII rules that Relay supports.
+#[cfg(any(feature = "web", test))]
+pub static REDACTION_METHODS: &[&'static str] = &["default", "remove", "replace", "mask", "hash"];
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct PiiConfig(pub serde_json::Map<String, serde_json::Value>);
 
 impl From<ProcessorPiiConfig> for PiiConfig {
@@ -20,23 +34,197 @@ impl From<ProcessorPiiConfig> for PiiConfig {
 }
 
 impl PiiConfig {
+    /// Wraps a JSON value, which has to be an object.
+    #[cfg(any(feature = "web", test))]
+    pub fn from_value(value: serde_json::Value) -> Result<PiiConfig, Error> {
+        match value {
+            serde_json::Value::Object(map) => Ok(PiiConfig(map)),
+            _ => Err(err_msg("The PII config is not an object")),
+        }
+    }
+
+    /// Returns the definition of a custom rule from the `rules` map.
+    #[cfg(any(feature = "web", test))]
+    pub fn get_rule(&self, rule: &str) -> Option<&serde_json::Value> {
+        self.0.get("rules").and_then(|rules| rules.get(rule))
+    }
+
+    /// Returns all `(selector, rule)` pairs from the `applications` map.
+    #[cfg(any(feature = "web", test))]
+    pub fn get_applications(&self) -> Vec<(String, String)> {
+        let applications = match self.0.get("applications").and_then(|x| x.as_object()) {
+            Some(applications) => applications,
+            None => return vec![],
+        };
+
+        applications
+            .iter()
+            .flat_map(|(selector, rules)| {
+                rules
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|rule| rule.as_str())
+                    .map(move |rule| (selector.clone(), rule.to_owned()))
+            })
+            .collect()
+    }
+
+    /// Parses the config for the PII processor, which compiles all of its patterns.
+    pub fn compile(&self) -> Result<CompiledPiiConfig, Error> {
+        let processor_config = serde_json::from_value(serde_json::Value::Object(self.0.clone()))
+            .context("Failed to parse PII config")?;
+
+        Ok(CompiledPiiConfig {
+            config: self.clone(),
+            processor_config,
+        })
+    }
+
+    #[cfg(any(feature = "web", test))]
     pub fn strip_event(&self, event: &SensitiveEvent) -> Result<StrippedEvent, Error> {
-        let config = ProcessorPiiConfig::from_json(&serde_json::to_string(&self.0).unwrap())?;
+        self.compile()?.strip_event(event)
+    }
+}
 
+/// A `PiiConfig` together with its parsed form. Use this instead of `PiiConfig::strip_event` to
+/// strip many events with the same config, so that it is only parsed once.
+pub struct CompiledPiiConfig {
+    // The command line only ever strips with the parsed form.
+    #[cfg_attr(not(feature = "web"), allow(dead_code))]
+    pub config: PiiConfig,
+    processor_config: ProcessorPiiConfig,
+}
+
+impl CompiledPiiConfig {
+    /// Runs the PII processor on a copy of the event and returns the typed result.
+    pub fn process_event(&self, event: &SensitiveEvent) -> Result<SensitiveEvent, Error> {
         let mut event = event.clone();
-        let mut processor = PiiProcessor::new(&config);
+        let mut processor = PiiProcessor::new(&self.processor_config);
         process_value(&mut event, &mut processor, &Default::default()).context("Failed to PII-strip event")?;
+        Ok(event)
+    }
 
-        let mut result =
-            StrippedEvent::from_json(&event.to_json().context("Failed to serialize PII'd event")?)
-                .context("Failed to parse PII'd event")?;
+    /// Runs the PII processor on a copy of the event and returns the result as a value tree.
+    pub fn strip_event(&self, event: &SensitiveEvent) -> Result<StrippedEvent, Error> {
+        Ok(to_value_tree(self.process_event(event)?))
+    }
+}
+
+/// Runs the normalization Relay applies to every event before it is stored, such as canonicalizing
+/// request headers, normalizing stacktraces and filling in timestamps.
+///
+/// `received_at` is the RFC 3339 time the event counts as received at. Relay reads the clock when it
+/// is missing, which panics on `wasm32-unknown-unknown`. The IP address of the client is not known
+/// here, so `{{auto}}` IP addresses are left as they are.
+#[cfg(feature = "web")]
+pub fn normalize_event(event: &mut SensitiveEvent, received_at: &str) -> Result<(), Error> {
+    let config: StoreConfig =
+        serde_json::from_value(json!({ "received_at": received_at })).context("Invalid time of receipt")?;
+    let mut processor = StoreProcessor::new(config, None);
+    process_value(event, &mut processor, ProcessingState::root()).context("Failed to normalize event")?;
+    Ok(())
+}
 
-        if let Some(ref mut value) = result.value_mut() {
-            if let Value::Object(ref mut map) = value {
-                map.remove("_meta");
+/// Appends a segment to a dotted path, where the empty path is the root of the event.
+pub fn join_path(path: &str, segment: &str) -> String {
+    if path.is_empty() {
+        segment.to_owned()
+    } else {
+        format!("{}.{}", path, segment)
+    }
+}
+
+/// Collects the paths of attributes that relay-general does not know. They are kept in the `other`
+/// map of the type they appear in, and only serialized back as they were.
+#[cfg(feature = "web")]
+struct OtherCollector {
+    paths: Vec<String>,
+}
+
+#[cfg(feature = "web")]
+impl Processor for OtherCollector {
+    fn process_other(&mut self, other: &mut Object<Value>, state: &ProcessingState) -> ProcessingResult {
+        let path = state.path().to_string();
+        for key in other.keys() {
+            self.paths.push(join_path(&path, key));
+        }
+
+        Ok(())
+    }
+}
+
+/// Returns the paths of all attributes of the event that relay-general kept in `other`.
+#[cfg(feature = "web")]
+pub fn get_other_paths(event: &SensitiveEvent) -> Result<Vec<String>, Error> {
+    let mut collector = OtherCollector { paths: vec![] };
+    process_value(&mut event.clone(), &mut collector, ProcessingState::root())
+        .context("Failed to collect unknown attributes")?;
+    Ok(collector.paths)
+}
+
+/// Converts an event to a value tree, keeping the meta of all values.
+///
+/// Fields that are missing and carry no meta are left out, like they are when the event is
+/// serialized.
+pub fn to_value_tree(event: SensitiveEvent) -> Annotated<Value> {
+    let mut value = event.map_value(ToValue::to_value);
+    remove_missing(&mut value);
+    value
+}
+
+fn remove_missing(value: &mut Annotated<Value>) {
+    match value.value_mut() {
+        Some(Value::Object(map)) => {
+            let missing: Vec<String> = map
+                .iter()
+                .filter(|(_, value)| value.value().is_none() && value.meta().is_empty())
+                .map(|(key, _)| key.clone())
+                .collect();
+
+            for key in missing {
+                map.remove(&key);
             }
+
+            for value in map.values_mut() {
+                remove_missing(value);
+            }
+        }
+        Some(Value::Array(values)) => {
+            for value in values {
+                remove_missing(value);
+            }
+        }
+        _ => (),
+    }
+}
+
+/// Parses the JSON of a stripped event, keeping the remarks in `_meta` as meta of the values.
+#[cfg(feature = "web")]
+pub fn parse_stripped_event(json: &str) -> Result<StrippedEvent, Error> {
+    let mut result = StrippedEvent::from_json(json).context("Failed to parse PII'd event")?;
+
+    if let Some(ref mut value) = result.value_mut() {
+        if let Value::Object(ref mut map) = value {
+            map.remove("_meta");
         }
+    }
+
+    Ok(result)
+}
 
-        Ok(result)
+/// Returns the ids of the rules that an `alias` or `multiple` rule refers to.
+#[cfg(any(feature = "web", test))]
+pub fn get_rule_references(rule: &serde_json::Value) -> Vec<&str> {
+    match rule.get("type").and_then(|x| x.as_str()) {
+        Some("alias") => rule.get("rule").and_then(|x| x.as_str()).into_iter().collect(),
+        Some("multiple") => rule
+            .get("rules")
+            .and_then(|x| x.as_array())
+            .into_iter()
+            .flatten()
+            .filter_map(|x| x.as_str())
+            .collect(),
+        _ => vec![],
     }
 }
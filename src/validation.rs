@@ -0,0 +1,333 @@
+use std::fmt;
+
+use failure::Error;
+use regex::Regex;
+use relay_general::pii::RuleSpec;
+use serde_json;
+use serde_yaml;
+use toml;
+
+use config_format::{parse_config, parse_config_auto, ConfigFormat};
+use envelope::{is_envelope, parse_input};
+use suggestions::PiiConfigExt;
+use types::{get_rule_references, REDACTION_METHODS};
+
+/// The editor a diagnostic refers to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Source {
+    Event,
+    Config,
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Source::Event => write!(f, "event"),
+            Source::Config => write!(f, "config"),
+        }
+    }
+}
+
+/// A single problem with the event or config input.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Diagnostic {
+    pub source: Source,
+    pub message: String,
+    /// The 1-based line and column the problem was found at, if known.
+    pub location: Option<(usize, usize)>,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.location {
+            Some((line, column)) => write!(f, "{}:{}:{}: {}", self.source, line, column, self.message),
+            None => write!(f, "{}: {}", self.source, self.message),
+        }
+    }
+}
+
+/// Extracts the 1-based line and column from the parse error in a failure chain.
+fn get_error_location(error: &Error) -> Option<(usize, usize)> {
+    for cause in error.iter_chain() {
+        if let Some(e) = cause.downcast_ref::<serde_json::Error>() {
+            return Some((e.line(), e.column()));
+        }
+        if let Some(e) = cause.downcast_ref::<serde_yaml::Error>() {
+            return e.location().map(|location| (location.line(), location.column()));
+        }
+        if let Some(e) = cause.downcast_ref::<toml::de::Error>() {
+            return e.line_col().map(|(line, column)| (line + 1, column + 1));
+        }
+    }
+
+    None
+}
+
+fn format_error(error: &Error) -> String {
+    error
+        .iter_chain()
+        .map(|cause| cause.to_string())
+        .collect::<Vec<_>>()
+        .join(": ")
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '-'
+}
+
+/// Finds the first occurrence of a key or value at or after `offset` and returns its start and
+/// end. Keys are followed by `:` or `=`, or by `.` or `]` in TOML table headers.
+///
+/// In JSON, the needle is searched as a quoted string. Elsewhere it must not be part of a longer
+/// word. Either way, a short rule id does not match inside of other keys.
+fn find_needle(input: &str, offset: usize, format: ConfigFormat, needle: &str, is_key: bool) -> Option<(usize, usize)> {
+    let needle = match format {
+        ConfigFormat::Json => serde_json::to_string(needle).ok()?,
+        _ => needle.to_owned(),
+    };
+
+    for (index, _) in input[offset..].match_indices(&needle) {
+        let start = offset + index;
+        let end = start + needle.len();
+        let before = input[..start].chars().next_back();
+        let mut after = input[end..].chars();
+
+        if format != ConfigFormat::Json {
+            if before.map_or(false, is_word_char) || input[end..].chars().next().map_or(false, is_word_char) {
+                continue;
+            }
+
+            // Skip the closing quote of a quoted key.
+            if (before == Some('"') || before == Some('\'')) && after.clone().next() == before {
+                after.next();
+            }
+        }
+
+        if !is_key {
+            return Some((start, end));
+        }
+
+        match after.find(|c: char| c != ' ' && c != '\t') {
+            Some(':') | Some('=') | Some('.') | Some(']') => return Some((start, end)),
+            _ => continue,
+        }
+    }
+
+    None
+}
+
+/// Finds the keys one after another in the input, followed by the value if given, and returns the
+/// 1-based line and column of the last one. This is how semantic errors are mapped back to the
+/// text of a config.
+fn locate(input: &str, format: ConfigFormat, keys: &[&str], value: Option<&str>) -> Option<(usize, usize)> {
+    let needles = keys
+        .iter()
+        .map(|key| (*key, true))
+        .chain(value.map(|value| (value, false)))
+        .collect::<Vec<_>>();
+
+    if needles.is_empty() {
+        return None;
+    }
+
+    let mut offset = 0;
+    let mut start = 0;
+
+    for (needle, is_key) in needles {
+        let (needle_start, needle_end) = find_needle(input, offset, format, needle, is_key)?;
+        start = needle_start;
+        offset = needle_end;
+    }
+
+    let before = &input[..start];
+    let line = before.matches('\n').count() + 1;
+    let column = before.rsplit('\n').next().unwrap_or("").chars().count() + 1;
+    Some((line, column))
+}
+
+/// Checks whether the event input parses.
+pub fn validate_event(input: &str) -> Vec<Diagnostic> {
+    match parse_input(input) {
+        Ok(_) => vec![],
+        Err(e) => vec![Diagnostic {
+            source: Source::Event,
+            message: format_error(&e),
+            // Locations of errors in envelope items are relative to the item, not the input.
+            location: if is_envelope(input) { None } else { get_error_location(&e) },
+        }],
+    }
+}
+
+/// Checks a config for syntax errors, invalid regexes, unknown redaction methods, references to
+/// rules that do not exist, and rules that Relay rejects for any other reason.
+pub fn validate_config(input: &str, format: Option<ConfigFormat>) -> Vec<Diagnostic> {
+    let parsed = match format {
+        Some(format) => parse_config(input, format).map(|config| (config, format)),
+        None => parse_config_auto(input),
+    };
+
+    let (config, format) = match parsed {
+        Ok(parsed) => parsed,
+        Err(e) => {
+            return vec![Diagnostic {
+                source: Source::Config,
+                message: format_error(&e),
+                location: get_error_location(&e),
+            }];
+        }
+    };
+
+    let mut rv = vec![];
+    let diagnostic = |message: String, keys: &[&str], value: Option<&str>| Diagnostic {
+        source: Source::Config,
+        message,
+        location: locate(input, format, keys, value),
+    };
+
+    let known_rules = config.get_known_rules();
+    let is_known_rule = |rule: &str| known_rules.iter().any(|x| x == rule);
+
+    for (id, rule) in config.0.get("rules").and_then(|x| x.as_object()).into_iter().flatten() {
+        let found = rv.len();
+
+        for &(key, name) in &[("pattern", "pattern"), ("keyPattern", "key pattern")] {
+            if let Some(pattern) = rule.get(key).and_then(|x| x.as_str()) {
+                if let Err(e) = Regex::new(pattern) {
+                    rv.push(diagnostic(
+                        format!("Rule {} has an invalid {}: {}", id, name, e),
+                        &[id.as_str(), key],
+                        None,
+                    ));
+                }
+            }
+        }
+
+        for reference in get_rule_references(rule) {
+            if !is_known_rule(reference) {
+                rv.push(diagnostic(
+                    format!("Rule {} refers to unknown rule {}", id, reference),
+                    &[id.as_str()],
+                    Some(reference),
+                ));
+            }
+        }
+
+        if let Some(method) = rule.get("redaction").and_then(|x| x.get("method")) {
+            let method = method.as_str().unwrap_or("");
+            if !REDACTION_METHODS.contains(&method) {
+                rv.push(diagnostic(
+                    format!("Rule {} has unknown redaction method {:?}", id, method),
+                    &[id.as_str(), "method"],
+                    None,
+                ));
+            }
+        }
+
+        // Relay knows best which rule types and options exist.
+        if rv.len() == found {
+            if let Err(e) = serde_json::from_value::<RuleSpec>(rule.clone()) {
+                rv.push(diagnostic(format!("Rule {} is invalid: {}", id, e), &[id.as_str()], None));
+            }
+        }
+    }
+
+    for (selector, rule) in config.get_applications() {
+        if !is_known_rule(&rule) {
+            rv.push(diagnostic(
+                format!("Selector {} refers to unknown rule {}", selector, rule),
+                &["applications", selector.as_str()],
+                Some(rule.as_str()),
+            ));
+        }
+    }
+
+    if rv.is_empty() {
+        // Catch everything the checks above do not know about.
+        if let Err(e) = config.compile() {
+            rv.push(diagnostic(format_error(&e), &[], None));
+        }
+    }
+
+    rv
+}
+
+/// Checks the event and config input and returns all problems found.
+pub fn validate(event: &str, config: &str, format: Option<ConfigFormat>) -> Vec<Diagnostic> {
+    let mut rv = validate_event(event);
+    rv.extend(validate_config(config, format));
+    rv
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_locations(input: &str, format: ConfigFormat) -> Vec<Option<(usize, usize)>> {
+        validate_config(input, Some(format))
+            .into_iter()
+            .map(|diagnostic| diagnostic.location)
+            .collect()
+    }
+
+    #[test]
+    fn test_locate_json() {
+        let input = r#"{
+  "rules": {
+    "ab": {"type": "alias", "rule": "a"},
+    "a": {"type": "pattern", "pattern": "["}
+  }
+}"#;
+
+        // Neither the key `ab` nor the reference to `a` is taken for the rule `a`.
+        assert_eq!(get_locations(input, ConfigFormat::Json), vec![Some((4, 30))]);
+    }
+
+    #[test]
+    fn test_locate_yaml() {
+        let input = "rules:\n  r1:\n    type: pattern\n    pattern: \"[\"\n";
+        assert_eq!(get_locations(input, ConfigFormat::Yaml), vec![Some((4, 5))]);
+    }
+
+    #[test]
+    fn test_locate_toml() {
+        let input = "[rules.r1]\ntype = \"pattern\"\npattern = \"[\"\n";
+        assert_eq!(get_locations(input, ConfigFormat::Toml), vec![Some((3, 1))]);
+    }
+
+    #[test]
+    fn test_locate_reference() {
+        let input = "rules:\n  r1:\n    type: alias\n    rule: missing\napplications:\n  $string: [r1, r12]\n";
+        let diagnostics = validate_config(input, None);
+
+        assert_eq!(
+            diagnostics
+                .iter()
+                .map(|diagnostic| (&*diagnostic.message, diagnostic.location))
+                .collect::<Vec<_>>(),
+            vec![
+                ("Rule r1 refers to unknown rule missing", Some((4, 11))),
+                ("Selector $string refers to unknown rule r12", Some((6, 17))),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unknown_redaction_method() {
+        let input = r#"{"rules": {"r1": {"type": "ip", "redaction": {"method": "shred"}}}}"#;
+        let diagnostics = validate_config(input, None);
+
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].message, "Rule r1 has unknown redaction method \"shred\"");
+        assert_eq!(diagnostics[0].location, Some((1, 47)));
+    }
+
+    #[test]
+    fn test_validate() {
+        assert_eq!(validate("{}", "{}", None), vec![]);
+
+        let diagnostics = validate("{\"message\": }", "{}", None);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].source, Source::Event);
+        assert_eq!(diagnostics[0].location, Some((1, 13)));
+    }
+}
@@ -0,0 +1,2083 @@
+use std::fmt;
+use std::mem;
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use failure::{err_msg, Error};
+use serde_json;
+use stdweb;
+use stdweb::web::event::IMouseEvent;
+use yew::agent::{Bridge, Bridged, Threaded};
+use yew::prelude::*;
+use yew::services::timeout::{TimeoutService, TimeoutTask};
+
+use relay_general::processor::ProcessingState;
+use relay_general::types::{Remark, Value};
+
+use permalink;
+use remarks::{remark_type_name, split_by_remarks};
+use rule_editor::{RuleField, RuleForm, RULE_TYPES};
+use config_format::{parse_config_as, serialize_config, ConfigFormat};
+use diff::{diff_values, Change};
+use fixtures::Fixture;
+use legacy::LegacySettings;
+use suggestions::{IneffectiveRule, PiiRuleSuggestion, RuleCover, RuleSuggestions};
+use trace::RedactionTrace;
+use validation::{Diagnostic, Source};
+use types::{parse_stripped_event, PiiConfig, StrippedEvent, REDACTION_METHODS};
+use worker::{
+    self, is_worker, IneffectiveRulesRequest, OutputItem, RecordFixturesRequest, Request, Response,
+    RunFixturesRequest, StripRequest, SuggestRequest, Worker,
+};
+use workspace::{Workspace, WorkspaceStorage};
+
+macro_rules! web_panic {
+    () => {
+        web_panic!("Internal error");
+    };
+
+    ($($args:tt)*) => {{
+        stdweb::web::alert(&format!($($args)*));
+        panic!();
+    }}
+}
+
+static DEFAULT_EVENT: &'static str = r#"{
+  "level": "warning",
+  "extra": {
+    "foo": [1, 2, 3, "127.0.0.1"]
+  },
+  "message": "Paid with card 4242 4242 4242 4242 on d/deadbeef1234"
+}"#;
+
+static DEFAULT_CONFIG: &'static str = r#"{
+  "rules": {
+    "device_id": {
+      "type": "pattern",
+      "pattern": "d/[a-f0-9]{12}",
+      "redaction": {
+        "method": "hash"
+      }
+    }
+  }
+}"#;
+
+static DEFAULT_LEGACY_SETTINGS: &'static str = r#"{
+  "dataScrubber": true,
+  "dataScrubberDefaults": true,
+  "scrubIPAddresses": false,
+  "sensitiveFields": [],
+  "safeFields": []
+}"#;
+
+/// How long to wait after the last keystroke before stripping the input.
+const STRIP_DELAY: Duration = Duration::from_millis(300);
+
+#[derive(PartialEq)]
+enum State {
+    Editing,
+    /// Waiting for the worker to find rules for the request.
+    SearchingPiiRules {
+        request: PiiRulesRequest,
+    },
+    SelectPiiRule {
+        request: PiiRulesRequest,
+        suggestions: RuleSuggestions,
+        traces: Vec<RedactionTrace>,
+    },
+}
+
+impl Renderable<PiiDemo> for PiiRuleSuggestion {
+    fn view(&self) -> Html<PiiDemo> {
+        let (config, text) = match *self {
+            PiiRuleSuggestion::ActivateRule {
+                ref rule,
+                ref config,
+                ..
+            } => (
+                config.clone(),
+                html! {
+                    <span>
+                        <input type="checkbox", />
+                        <code>{ &rule }</code>
+                    </span>
+                },
+            ),
+            PiiRuleSuggestion::DeactivateRule {
+                ref rule,
+                ref config,
+                ..
+            } => (
+                config.clone(),
+                html! {
+                    <span>
+                    <input type="checkbox", checked=true, />
+                        <code>{ &rule }</code>
+                    </span>
+                },
+            ),
+            PiiRuleSuggestion::CreateRule {
+                ref pattern,
+                ref method,
+                ref config,
+                ..
+            } => (
+                config.clone(),
+                html! {
+                    <span class="magic-rule",>
+                        <input type="checkbox", />
+                        { "New rule " }<code>{ &pattern }</code>{ " with " }<code>{ &method }</code>
+                    </span>
+                },
+            ),
+        };
+
+        html! {
+            <li><a
+                class="rule-choice",
+                onclick=|_| Msg::ApplyPiiConfig(config.clone()),>
+                { text }
+                { view_collateral(self.collateral()) }
+            </a></li>
+        }
+    }
+}
+
+impl Renderable<PiiDemo> for RuleCover {
+    fn view(&self) -> Html<PiiDemo> {
+        let config = self.config.clone();
+
+        html! {
+            <div class="pii-kind-section rule-cover",>
+                <h3>{ "No single rule matches all values, but these do together" }</h3>
+                <ul>
+                    <li><a
+                        class="rule-choice",
+                        onclick=|_| Msg::ApplyPiiConfig(config.clone()),>
+                        { for self.suggestions.iter().map(view_cover_part) }
+                        { view_collateral(&self.collateral) }
+                    </a></li>
+                </ul>
+            </div>
+        }
+    }
+}
+
+fn view_fixture_result(name: &str, result: &Result<Vec<Change>, String>) -> Html<PiiDemo> {
+    match result {
+        Ok(changes) if changes.is_empty() => html! {
+            <div class="fixture-result passed",><strong>{ name }</strong>{ ": ok" }</div>
+        },
+        Ok(changes) => html! {
+            <div class="fixture-result failed",>
+                <strong>{ name }</strong>
+                { view_changes(changes, "Expected", "Actual") }
+            </div>
+        },
+        Err(e) => html! {
+            <div class="fixture-result failed",><strong>{ name }</strong>{ format!(": {}", e) }</div>
+        },
+    }
+}
+
+fn view_changes(changes: &[Change], before: &str, after: &str) -> Html<PiiDemo> {
+    html! {
+        <table class="diff",>
+            <tr>
+                <th>{ "Path" }</th>
+                <th>{ before }</th>
+                <th>{ after }</th>
+            </tr>
+            {
+                for changes.iter().map(|change| html! {
+                    <tr class={ format!("change {}", change.kind.as_str()) },>
+                        <td class="path",><code>{ &change.path }</code></td>
+                        <td class="json original",>{ change.original.payload_to_json().unwrap_or_default() }</td>
+                        <td class="json stripped",>{ change.stripped.payload_to_json().unwrap_or_default() }</td>
+                    </tr>
+                })
+            }
+        </table>
+    }
+}
+
+/// Shows what parsing and, if enabled, normalization changed about the pasted event.
+fn view_parse_changes(item: &StrippedItem, normalize: bool) -> Html<PiiDemo> {
+    let parse_changes = if item.parse_changes.is_empty() && item.other_paths.is_empty() {
+        html! {
+            <p>{ "relay-general kept every field of this event as it was pasted." }</p>
+        }
+    } else if item.parse_changes.is_empty() {
+        "".into()
+    } else {
+        view_changes(&item.parse_changes, "Pasted", "Parsed")
+    };
+
+    let other_paths = &item.other_paths;
+    let other_paths = if other_paths.is_empty() {
+        "".into()
+    } else {
+        html! {
+            <div class="other-paths",>
+                { "Unknown attributes, kept in " }<code>{ "other" }</code>{ ": " }
+                { for other_paths.iter().map(|path| html! { <code>{ path }</code> }) }
+            </div>
+        }
+    };
+
+    let normalization_changes = if !normalize {
+        "".into()
+    } else if item.normalization_changes.is_empty() {
+        html! {
+            <p>{ "Normalization did not change this event." }</p>
+        }
+    } else {
+        view_changes(&item.normalization_changes, "Parsed", "Normalized")
+    };
+
+    html! {
+        <div class="parse-changes",>
+            <h3>{ "Parsing" }</h3>
+            { parse_changes }
+            { other_paths }
+            {
+                if normalize {
+                    html! { <h3>{ "Normalization" }</h3> }
+                } else {
+                    "".into()
+                }
+            }
+            { normalization_changes }
+        </div>
+    }
+}
+
+fn view_cover_part(suggestion: &PiiRuleSuggestion) -> Html<PiiDemo> {
+    let change = match *suggestion {
+        PiiRuleSuggestion::ActivateRule { ref rule, .. } => html! {
+            <span>{ "add " }<code>{ rule }</code></span>
+        },
+        PiiRuleSuggestion::DeactivateRule { ref rule, .. } => html! {
+            <span>{ "remove " }<code>{ rule }</code></span>
+        },
+        PiiRuleSuggestion::CreateRule {
+            ref pattern,
+            ref method,
+            ..
+        } => html! {
+            <span class="magic-rule",>
+                { "new rule " }<code>{ pattern }</code>{ " with " }<code>{ method }</code>
+            </span>
+        },
+    };
+
+    html! {
+        <span class="cover-part",>
+            { change }{ " on " }<code>{ suggestion.pii_kind() }</code>
+        </span>
+    }
+}
+
+fn view_collateral(collateral: &[Change]) -> Html<PiiDemo> {
+    if collateral.is_empty() {
+        return "".into();
+    }
+
+    let preview = collateral
+        .iter()
+        .map(|change| {
+            format!(
+                "{}: {} \u{2192} {}",
+                change.path,
+                change.original.payload_to_json().unwrap_or_default(),
+                change.stripped.payload_to_json().unwrap_or_default()
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let label = if collateral.len() == 1 {
+        "+1 other value".to_owned()
+    } else {
+        format!("+{} other values", collateral.len())
+    };
+
+    html! {
+        <span class="collateral", title=preview,>{ label }</span>
+    }
+}
+
+/// An input event before and after stripping, as returned by the worker.
+struct StrippedItem {
+    /// The event as it was passed to the PII processor, after normalization if enabled.
+    input: StrippedEvent,
+    stripped: StrippedEvent,
+    /// The values of the pasted JSON that relay-general dropped, changed or added.
+    parse_changes: Vec<Change>,
+    /// The values of the parsed event that normalization changed.
+    normalization_changes: Vec<Change>,
+    /// The paths of attributes that relay-general does not know and kept in `other`.
+    other_paths: Vec<String>,
+}
+
+impl StrippedItem {
+    fn parse(item: worker::StrippedItem) -> Result<StrippedItem, Error> {
+        Ok(StrippedItem {
+            input: parse_stripped_event(&item.input)?,
+            stripped: parse_stripped_event(&item.stripped)?,
+            parse_changes: item.parse_changes,
+            normalization_changes: item.normalization_changes,
+            other_paths: item.other_paths,
+        })
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum ViewMode {
+    Stripped,
+    Diff,
+}
+
+impl State {
+    fn get_request(&self) -> Option<&PiiRulesRequest> {
+        match *self {
+            State::Editing => None,
+            State::SearchingPiiRules { ref request } => Some(request),
+            State::SelectPiiRule { ref request, .. } => Some(request),
+        }
+    }
+}
+
+impl fmt::Display for State {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            State::Editing => write!(f, "editing")?,
+            State::SearchingPiiRules { .. } => write!(f, "searching-pii-rules")?,
+            State::SelectPiiRule { .. } => write!(f, "select-pii-rule")?,
+        }
+        Ok(())
+    }
+}
+
+struct PiiDemo {
+    event: String,
+    config: String,
+    /// Paths of values that suggestions must not change.
+    pins: Vec<String>,
+    /// Whether to normalize events like Relay before stripping them.
+    normalize: bool,
+    state: State,
+    storage: WorkspaceStorage,
+    workspaces: BTreeMap<String, Workspace>,
+    workspace_name: String,
+    view_mode: ViewMode,
+    /// Whether the column with the event as relay-general parsed and normalized it is shown.
+    show_normalized: bool,
+    /// The format of the config editor, or `None` to detect it from the input.
+    config_format: Option<ConfigFormat>,
+    /// The parsed config editor, updated whenever its text or format changes.
+    pii_config: Result<PiiConfig, Error>,
+    effective_config_format: ConfigFormat,
+    rule_form: Option<RuleForm>,
+    /// The legacy data scrubbing settings being imported, if the importer is open.
+    legacy_settings: Option<String>,
+    /// The result of the last search for ineffective rules, until the input changes.
+    ineffective_rules: Option<Vec<IneffectiveRule>>,
+    /// The id of the pending search for ineffective rules.
+    ineffective_rules_request_id: Option<u64>,
+    fixtures: BTreeMap<String, Fixture>,
+    fixture_name: String,
+    /// The id of the pending recording of fixtures, and the name to save them under.
+    record_fixtures_request: Option<(u64, String)>,
+    /// The result of the last fixture run against the current config, until the config changes.
+    fixture_results: Option<Vec<(String, Result<Vec<Change>, String>)>>,
+    /// The id of the pending fixture run.
+    run_fixtures_request_id: Option<u64>,
+    link: ComponentLink<PiiDemo>,
+    /// Strips events and searches for suggestions, so that the page stays responsive. This is only
+    /// `None` while the worker is being replaced.
+    worker: Option<Box<dyn Bridge<Worker>>>,
+    /// The requests sent to the worker that it has not answered yet.
+    in_flight: Vec<Request>,
+    timeout: TimeoutService,
+    /// The pending delayed strip. Dropping it cancels it.
+    strip_task: Option<TimeoutTask>,
+    next_request_id: u64,
+    /// The ids of the latest requests to the worker. Responses to older requests are ignored.
+    strip_request_id: u64,
+    suggest_request_id: u64,
+    /// Whether the stripped items are out of date with the input.
+    stripping: bool,
+    /// The output of the last strip response of the worker.
+    diagnostics: Vec<Diagnostic>,
+    show_item_headers: bool,
+    output_items: Vec<OutputItem<StrippedItem>>,
+    lints: Vec<String>,
+}
+
+impl PiiDemo {
+    fn get_workspace(&self) -> Workspace {
+        Workspace {
+            event: self.event.clone(),
+            config: self.config.clone(),
+            pins: self.pins.clone(),
+            normalize: self.normalize,
+        }
+    }
+
+    fn set_workspace(&mut self, workspace: Workspace) {
+        self.event = workspace.event;
+        self.config = workspace.config;
+        self.pins = workspace.pins;
+        self.normalize = workspace.normalize;
+        self.state = State::Editing;
+        self.reset_ineffective_rules();
+        self.update_pii_config();
+        self.schedule_strip();
+    }
+
+    /// Parses the config editor again after its text or format changed.
+    fn update_pii_config(&mut self) {
+        self.pii_config = parse_config_as(&self.config, self.config_format);
+        self.effective_config_format = self
+            .config_format
+            .or_else(|| ConfigFormat::detect(&self.config))
+            .unwrap_or(ConfigFormat::Json);
+    }
+
+    /// Forgets the ineffective rules, including a pending search for them, after the input changed.
+    fn reset_ineffective_rules(&mut self) {
+        self.ineffective_rules = None;
+        self.ineffective_rules_request_id = None;
+    }
+
+    fn get_request_id(&mut self) -> u64 {
+        self.next_request_id += 1;
+        self.next_request_id
+    }
+
+    /// Sends a request to the worker.
+    ///
+    /// If a request of the same kind has not been answered yet, the worker may be busy with it and
+    /// cannot be interrupted. The worker is then replaced by a new one, and all other unanswered
+    /// requests are sent to it again.
+    fn send_request(&mut self, request: Request) {
+        let kind = mem::discriminant(&request);
+
+        if self.in_flight.iter().any(|pending| mem::discriminant(pending) == kind) {
+            let resend = mem::replace(&mut self.in_flight, vec![])
+                .into_iter()
+                .filter(|pending| mem::discriminant(pending) != kind)
+                .collect::<Vec<_>>();
+
+            // The old bridge has to be dropped first, otherwise the new one connects to the same
+            // busy worker.
+            self.worker = None;
+            self.worker = Some(Worker::bridge(self.link.send_back(Msg::WorkerResponse)));
+
+            for pending in resend {
+                self.send_request(pending);
+            }
+        }
+
+        self.in_flight.push(request.clone());
+        if let Some(ref mut worker) = self.worker {
+            worker.send(request);
+        }
+    }
+
+    /// Strips the input once it has not changed for a while.
+    fn schedule_strip(&mut self) {
+        self.stripping = true;
+        let callback = self.link.send_back(|_| Msg::Strip);
+        self.strip_task = Some(self.timeout.spawn(STRIP_DELAY, callback));
+    }
+
+    fn strip(&mut self) {
+        self.strip_task = None;
+
+        let id = self.get_request_id();
+        self.strip_request_id = id;
+        self.stripping = true;
+        self.send_request(Request::Strip(StripRequest {
+            id,
+            event: self.event.clone(),
+            config: self.config.clone(),
+            config_format: self.config_format,
+            normalize: self.normalize,
+        }));
+    }
+
+    fn search_pii_rules(&mut self, request: PiiRulesRequest) {
+        let config = match self.get_pii_config() {
+            Ok(config) => config,
+            // The diagnostics under the config explain what is wrong with it.
+            Err(_) => {
+                self.state = State::Editing;
+                return;
+            }
+        };
+
+        let id = self.get_request_id();
+        self.suggest_request_id = id;
+        self.send_request(Request::Suggest(SuggestRequest {
+            id,
+            event: self.event.clone(),
+            config,
+            item: request.item,
+            paths: request.paths.clone(),
+            pins: self.pins.clone(),
+            normalize: self.normalize,
+        }));
+        self.state = State::SearchingPiiRules { request };
+    }
+
+    fn get_pii_config(&self) -> Result<PiiConfig, Error> {
+        match self.pii_config {
+            Ok(ref config) => Ok(config.clone()),
+            Err(ref e) => Err(err_msg(e.to_string())),
+        }
+    }
+}
+
+#[derive(PartialEq, Eq)]
+struct PiiRulesRequest {
+    /// The index of the input item the values belong to.
+    item: usize,
+    paths: Vec<String>,
+}
+
+impl Renderable<PiiDemo> for PiiRulesRequest {
+    fn view(&self) -> Html<PiiDemo> {
+        html! {
+            <h2>
+                { "Select rule for " }
+                { for self.paths.iter().map(|path| html! { <code class="selected-path",>{ path }</code> }) }
+            </h2>
+        }
+    }
+}
+
+/// Shift-clicking a value adds it to the current selection instead of starting a new one.
+fn select_value(event: &ClickEvent, item: usize, path: String) -> Msg {
+    if event.shift_key() {
+        Msg::TogglePiiRulePath(item, path)
+    } else {
+        Msg::SelectPiiRule(PiiRulesRequest {
+            item,
+            paths: vec![path],
+        })
+    }
+}
+
+enum Msg {
+    PiiConfigChanged(String),
+    ApplyPiiConfig(PiiConfig),
+    SetConfigFormat(Option<ConfigFormat>),
+    ExportPiiConfig(ConfigFormat),
+    EventInputChanged(String),
+    SelectPiiRule(PiiRulesRequest),
+    TogglePiiRulePath(usize, String),
+    PinValues(Vec<String>),
+    UnpinValue(String),
+    OpenLegacyImport,
+    LegacySettingsChanged(String),
+    ImportLegacySettings,
+    CloseLegacyImport,
+    FixtureNameChanged(String),
+    SaveFixture,
+    DeleteFixture(String),
+    CopyFixture(String),
+    RunFixtures,
+    StartEditing,
+    CopyPermalink,
+    WorkspaceNameChanged(String),
+    SaveWorkspace,
+    LoadWorkspace(String),
+    DeleteWorkspace(String),
+    SetViewMode(ViewMode),
+    ShowNormalized(bool),
+    SetNormalize(bool),
+    EditRule(Option<String>),
+    RuleFormChanged(RuleField, String),
+    CloseRuleForm,
+    GoToDiagnostic(Source, usize, usize),
+    FindIneffectiveRules,
+    Strip,
+    WorkerResponse(Response),
+}
+
+impl Component for PiiDemo {
+    // Some details omitted. Explore the examples to see more.
+
+    type Message = Msg;
+    type Properties = ();
+
+    fn create(_: Self::Properties, mut link: ComponentLink<Self>) -> Self {
+        let mut storage = WorkspaceStorage::new();
+        let linked = permalink::load().map(|(event, config)| Workspace {
+            event,
+            config,
+            pins: vec![],
+            normalize: false,
+        });
+
+        // The link becomes the current workspace, so later edits survive a reload. The workspace it
+        // replaces may have unsaved edits, so it is saved under a name first.
+        if let Some(ref workspace) = linked {
+            if let Some(previous) = storage.load_current() {
+                let mut saved = storage.load_saved();
+                if previous != *workspace && !saved.values().any(|x| *x == previous) {
+                    let mut name = "Before opening a link".to_owned();
+                    let mut counter = 1;
+                    while saved.contains_key(&name) {
+                        counter += 1;
+                        name = format!("Before opening a link ({})", counter);
+                    }
+                    saved.insert(name, previous);
+                    storage.store_saved(&saved);
+                }
+            }
+
+            storage.store_current(workspace);
+            permalink::clear();
+        }
+
+        let workspace = linked
+            .or_else(|| storage.load_current())
+            .unwrap_or_else(|| Workspace {
+                event: DEFAULT_EVENT.to_owned(),
+                config: DEFAULT_CONFIG.to_owned(),
+                pins: vec![],
+                normalize: false,
+            });
+
+        let worker = Some(Worker::bridge(link.send_back(Msg::WorkerResponse)));
+
+        let mut pii_demo = PiiDemo {
+            config: workspace.config,
+            event: workspace.event,
+            pins: workspace.pins,
+            normalize: workspace.normalize,
+            state: State::Editing,
+            workspaces: storage.load_saved(),
+            workspace_name: String::new(),
+            view_mode: ViewMode::Stripped,
+            show_normalized: false,
+            config_format: None,
+            pii_config: Err(err_msg("Config not parsed yet")),
+            effective_config_format: ConfigFormat::Json,
+            rule_form: None,
+            legacy_settings: None,
+            ineffective_rules: None,
+            ineffective_rules_request_id: None,
+            fixtures: storage.load_fixtures(),
+            fixture_name: String::new(),
+            record_fixtures_request: None,
+            fixture_results: None,
+            run_fixtures_request_id: None,
+            storage,
+            link,
+            worker,
+            in_flight: vec![],
+            timeout: TimeoutService::new(),
+            strip_task: None,
+            next_request_id: 0,
+            strip_request_id: 0,
+            suggest_request_id: 0,
+            stripping: false,
+            diagnostics: vec![],
+            show_item_headers: false,
+            output_items: vec![],
+            lints: vec![],
+        };
+
+        pii_demo.update_pii_config();
+        pii_demo.strip();
+        pii_demo
+    }
+
+    fn update(&mut self, msg: Self::Message) -> ShouldRender {
+        if let Msg::WorkerResponse(ref response) = msg {
+            let id = response.id();
+            self.in_flight.retain(|request| request.id() != id);
+        }
+
+        match msg {
+            Msg::PiiConfigChanged(value) => {
+                self.config = value;
+                self.update_pii_config();
+                self.reset_ineffective_rules();
+                self.fixture_results = None;
+                self.run_fixtures_request_id = None;
+                self.storage.store_current(&self.get_workspace());
+                self.schedule_strip();
+                let mut state = State::Editing;
+                mem::swap(&mut state, &mut self.state);
+                if let State::SelectPiiRule { request, .. } | State::SearchingPiiRules { request } = state {
+                    self.search_pii_rules(request);
+                }
+            }
+            Msg::ApplyPiiConfig(config) => {
+                match serialize_config(&config, self.effective_config_format) {
+                    Ok(value) => return self.update(Msg::PiiConfigChanged(value)),
+                    Err(e) => web_panic!("{:}", e),
+                }
+            }
+            Msg::SetConfigFormat(config_format) => {
+                self.config_format = config_format;
+                self.update_pii_config();
+                self.schedule_strip();
+            }
+            Msg::ExportPiiConfig(format) => {
+                let exported = self
+                    .get_pii_config()
+                    .and_then(|config| serialize_config(&config, format));
+
+                match exported {
+                    Ok(value) => {
+                        if self.config_format.is_some() {
+                            self.config_format = Some(format);
+                        }
+                        return self.update(Msg::PiiConfigChanged(value));
+                    }
+                    Err(e) => {
+                        stdweb::web::alert(&format!("Cannot export config: {}", e));
+                        return false;
+                    }
+                }
+            }
+            Msg::EventInputChanged(value) => {
+                self.event = value;
+                self.reset_ineffective_rules();
+                self.state = State::Editing;
+                self.storage.store_current(&self.get_workspace());
+                self.schedule_strip();
+            }
+            Msg::SelectPiiRule(request) => {
+                self.search_pii_rules(request);
+            }
+            Msg::TogglePiiRulePath(item, path) => {
+                let mut request = PiiRulesRequest { item, paths: vec![] };
+                if let Some(current) = self.state.get_request() {
+                    if current.item == item {
+                        request.paths = current.paths.clone();
+                    }
+                }
+
+                match request.paths.iter().position(|x| *x == path) {
+                    Some(index) => {
+                        request.paths.remove(index);
+                    }
+                    None => request.paths.push(path),
+                }
+
+                if request.paths.is_empty() {
+                    self.state = State::Editing;
+                } else {
+                    return self.update(Msg::SelectPiiRule(request));
+                }
+            }
+            Msg::PinValues(paths) => {
+                for path in paths {
+                    if !self.pins.contains(&path) {
+                        self.pins.push(path);
+                    }
+                }
+                self.state = State::Editing;
+                self.storage.store_current(&self.get_workspace());
+            }
+            Msg::UnpinValue(path) => {
+                self.pins.retain(|x| *x != path);
+                self.storage.store_current(&self.get_workspace());
+                let mut state = State::Editing;
+                mem::swap(&mut state, &mut self.state);
+                if let State::SelectPiiRule { request, .. } | State::SearchingPiiRules { request } = state {
+                    self.search_pii_rules(request);
+                }
+            }
+            Msg::OpenLegacyImport => {
+                self.legacy_settings = Some(DEFAULT_LEGACY_SETTINGS.to_owned());
+            }
+            Msg::LegacySettingsChanged(value) => {
+                self.legacy_settings = Some(value);
+                return false;
+            }
+            Msg::ImportLegacySettings => {
+                let imported = self
+                    .legacy_settings
+                    .as_ref()
+                    .ok_or_else(|| err_msg("No legacy settings"))
+                    .and_then(|input| LegacySettings::from_json(input))
+                    .and_then(|settings| settings.to_pii_config());
+
+                match imported {
+                    Ok(config) => {
+                        self.legacy_settings = None;
+                        return self.update(Msg::ApplyPiiConfig(config));
+                    }
+                    Err(e) => {
+                        stdweb::web::alert(&format!("Cannot import settings: {}", e));
+                        return false;
+                    }
+                }
+            }
+            Msg::CloseLegacyImport => {
+                self.legacy_settings = None;
+            }
+            Msg::FixtureNameChanged(value) => {
+                self.fixture_name = value;
+            }
+            Msg::SaveFixture => {
+                let name = self.fixture_name.trim().to_owned();
+                if name.is_empty() {
+                    return false;
+                }
+
+                let config = match self.get_pii_config() {
+                    Ok(config) => config,
+                    Err(e) => {
+                        stdweb::web::alert(&format!("Cannot save fixture: {}", e));
+                        return false;
+                    }
+                };
+
+                let id = self.get_request_id();
+                self.record_fixtures_request = Some((id, name));
+                self.send_request(Request::RecordFixtures(RecordFixturesRequest {
+                    id,
+                    event: self.event.clone(),
+                    config,
+                }));
+                return false;
+            }
+            Msg::DeleteFixture(name) => {
+                self.fixtures.remove(&name);
+                self.storage.store_fixtures(&self.fixtures);
+                self.fixture_results = None;
+                self.run_fixtures_request_id = None;
+            }
+            Msg::CopyFixture(name) => {
+                let json = match self.fixtures.get(&name).map(serde_json::to_string_pretty) {
+                    Some(Ok(json)) => json,
+                    _ => return false,
+                };
+
+                js! { @(no_return)
+                    var json = @{json};
+                    if (navigator.clipboard) {
+                        navigator.clipboard.writeText(json);
+                    } else {
+                        window.prompt("Copy this fixture:", json);
+                    }
+                }
+
+                return false;
+            }
+            Msg::RunFixtures => {
+                let config = match self.get_pii_config() {
+                    Ok(config) => config,
+                    Err(e) => {
+                        stdweb::web::alert(&format!("Cannot run fixtures: {}", e));
+                        return false;
+                    }
+                };
+
+                let id = self.get_request_id();
+                self.run_fixtures_request_id = Some(id);
+                self.send_request(Request::RunFixtures(RunFixturesRequest {
+                    id,
+                    fixtures: self.fixtures.clone(),
+                    config,
+                }));
+            }
+            Msg::StartEditing => {
+                if self.state == State::Editing {
+                    return false;
+                }
+                self.state = State::Editing;
+            }
+            Msg::CopyPermalink => {
+                if let Err(e) = permalink::copy_link(&self.event, &self.config) {
+                    web_panic!("{:}", e);
+                }
+                return false;
+            }
+            Msg::WorkspaceNameChanged(value) => {
+                self.workspace_name = value;
+            }
+            Msg::SaveWorkspace => {
+                let name = self.workspace_name.trim().to_owned();
+                if name.is_empty() {
+                    return false;
+                }
+                self.workspaces.insert(name, self.get_workspace());
+                self.storage.store_saved(&self.workspaces);
+                self.workspace_name.clear();
+            }
+            Msg::LoadWorkspace(name) => {
+                let workspace = match self.workspaces.get(&name) {
+                    Some(workspace) => workspace.clone(),
+                    None => return false,
+                };
+                self.set_workspace(workspace);
+                self.storage.store_current(&self.get_workspace());
+            }
+            Msg::DeleteWorkspace(name) => {
+                self.workspaces.remove(&name);
+                self.storage.store_saved(&self.workspaces);
+            }
+            Msg::SetViewMode(view_mode) => {
+                if self.view_mode == view_mode {
+                    return false;
+                }
+                self.view_mode = view_mode;
+            }
+            Msg::ShowNormalized(show_normalized) => {
+                self.show_normalized = show_normalized;
+            }
+            Msg::SetNormalize(normalize) => {
+                self.normalize = normalize;
+                self.reset_ineffective_rules();
+                self.storage.store_current(&self.get_workspace());
+                self.schedule_strip();
+                let mut state = State::Editing;
+                mem::swap(&mut state, &mut self.state);
+                if let State::SelectPiiRule { request, .. } | State::SearchingPiiRules { request } = state {
+                    self.search_pii_rules(request);
+                }
+            }
+            Msg::EditRule(rule) => {
+                let config = match self.get_pii_config() {
+                    Ok(config) => config,
+                    Err(e) => {
+                        stdweb::web::alert(&format!("Cannot edit rules of an invalid config: {}", e));
+                        return false;
+                    }
+                };
+
+                self.rule_form = match rule {
+                    Some(rule) => RuleForm::from_rule(&config, &rule),
+                    None => Some(RuleForm::new(&config)),
+                };
+            }
+            Msg::RuleFormChanged(field, value) => {
+                let mut rule_form = match self.rule_form.take() {
+                    Some(rule_form) => rule_form,
+                    None => return false,
+                };
+
+                rule_form.set(field, value);
+                let result = self.get_pii_config().and_then(|config| rule_form.write(config));
+                self.rule_form = Some(rule_form);
+
+                if let Ok(config) = result {
+                    return self.update(Msg::ApplyPiiConfig(config));
+                }
+            }
+            Msg::CloseRuleForm => {
+                self.rule_form = None;
+            }
+            Msg::FindIneffectiveRules => {
+                let config = match self.get_pii_config() {
+                    Ok(config) => config,
+                    Err(e) => {
+                        stdweb::web::alert(&format!("Cannot analyze rules: {}", e));
+                        return false;
+                    }
+                };
+
+                let id = self.get_request_id();
+                self.ineffective_rules_request_id = Some(id);
+                self.send_request(Request::FindIneffectiveRules(IneffectiveRulesRequest {
+                    id,
+                    event: self.event.clone(),
+                    config,
+                    normalize: self.normalize,
+                }));
+            }
+            Msg::Strip => {
+                self.strip();
+                return false;
+            }
+            Msg::WorkerResponse(Response::Stripped {
+                id,
+                diagnostics,
+                envelope,
+                items,
+                lints,
+            }) => {
+                if id != self.strip_request_id {
+                    return false;
+                }
+
+                self.stripping = false;
+                self.diagnostics = diagnostics;
+                self.show_item_headers = envelope;
+                self.output_items = items.into_iter().map(|item| item.map(StrippedItem::parse)).collect();
+                self.lints = lints;
+            }
+            Msg::WorkerResponse(Response::Suggested { id, result }) => {
+                if id != self.suggest_request_id {
+                    return false;
+                }
+
+                let request = match mem::replace(&mut self.state, State::Editing) {
+                    State::SearchingPiiRules { request } => request,
+                    // The popup was closed in the meantime.
+                    state => {
+                        self.state = state;
+                        return false;
+                    }
+                };
+
+                match result {
+                    Ok((suggestions, traces)) => {
+                        self.state = State::SelectPiiRule {
+                            request,
+                            suggestions,
+                            traces,
+                        };
+                    }
+                    Err(e) => stdweb::web::alert(&format!("Cannot find rules: {}", e)),
+                }
+            }
+            Msg::WorkerResponse(Response::IneffectiveRules { id, result }) => {
+                if self.ineffective_rules_request_id != Some(id) {
+                    return false;
+                }
+
+                self.ineffective_rules_request_id = None;
+                match result {
+                    Ok(ineffective_rules) => self.ineffective_rules = Some(ineffective_rules),
+                    Err(e) => stdweb::web::alert(&format!("Cannot analyze rules: {}", e)),
+                }
+            }
+            Msg::WorkerResponse(Response::FixturesRecorded { id, result }) => {
+                let name = match self.record_fixtures_request.take() {
+                    Some((pending_id, name)) if pending_id == id => name,
+                    pending => {
+                        self.record_fixtures_request = pending;
+                        return false;
+                    }
+                };
+
+                let recorded = match result {
+                    Ok(recorded) => recorded,
+                    Err(e) => {
+                        stdweb::web::alert(&format!("Cannot save fixture: {}", e));
+                        return false;
+                    }
+                };
+
+                // Envelopes with several events get one fixture per event.
+                let count = recorded.len();
+                for (index, fixture) in recorded.into_iter().enumerate() {
+                    let key = if count == 1 {
+                        name.clone()
+                    } else {
+                        format!("{} #{}", name, index + 1)
+                    };
+                    self.fixtures.insert(key, fixture);
+                }
+
+                self.storage.store_fixtures(&self.fixtures);
+                self.fixture_name.clear();
+                self.fixture_results = None;
+                self.run_fixtures_request_id = None;
+            }
+            Msg::WorkerResponse(Response::FixturesRun { id, results }) => {
+                if self.run_fixtures_request_id != Some(id) {
+                    return false;
+                }
+
+                self.run_fixtures_request_id = None;
+                self.fixture_results = Some(results);
+            }
+            Msg::GoToDiagnostic(source, line, column) => {
+                let id = match source {
+                    Source::Event => "event-input",
+                    Source::Config => "config-input",
+                };
+                let (line, column) = (line as u32, column as u32);
+
+                js! { @(no_return)
+                    var textarea = document.getElementById(@{id});
+                    var lines = textarea.value.split("\n");
+                    var offset = 0;
+                    for (var i = 0; i < @{line} - 1 && i < lines.length; i++) {
+                        offset += lines[i].length + 1;
+                    }
+                    offset += @{column} - 1;
+                    textarea.focus();
+                    textarea.setSelectionRange(offset, offset);
+                }
+                return false;
+            }
+        }
+
+        true
+    }
+}
+
+impl Renderable<PiiDemo> for PiiDemo {
+    fn view(&self) -> Html<Self> {
+        html! {
+            <div class={ format!("state-{}", self.state).to_lowercase() },>
+                <title>{ "Piinguin: PII processing playground" }</title>
+                <link
+                    rel="stylesheet",
+                    href="./style.css", />
+                <div class={ if self.show_normalized { "table with-normalized" } else { "table" } },>
+                    <div class="col",>
+                        <div
+                            class="col-header",
+                            onclick=|_| Msg::StartEditing, >
+                            <h1>
+                                { "1. Paste an event you want to strip sensitive data from. " }
+                                <br/>
+                                <small>{ "This website does not send anything to a server." }</small>
+                            </h1>
+                            <button
+                                class="permalink",
+                                title="Links contain the event and config, but only in the part of the URL that is not sent to servers.",
+                                onclick=|_| Msg::CopyPermalink, >
+                                { "Copy link" }
+                            </button>
+                        </div>
+                        <textarea
+                            class="col-body",
+                            id="event-input",
+                            value=&self.event,
+                            onfocus=|_| Msg::StartEditing,
+                            oninput=|e| Msg::EventInputChanged(e.value), />
+                        { self.view_pins() }
+                        { self.view_workspaces() }
+                        { self.view_fixtures() }
+                    </div>
+                    { self.view_normalized_column() }
+                    <div class="col",>
+                        <div
+                            class="col-header",
+                            onclick=|_| Msg::StartEditing, >
+                            <h1>{ "2. Click on values you want to remove." }</h1>
+                            { self.view_mode_toggle() }
+                            { self.view_progress() }
+                        </div>
+                        { self.state.view() }
+                        <div
+                            class="col-body",
+                            onclick=|_| Msg::StartEditing, >
+                            { self.view_output() }
+                        </div>
+                    </div>
+                    <div class="col",>
+                        <div
+                            class="col-header",
+                            onclick=|_| Msg::StartEditing, >
+                            <h1>{ "3. Copy the PII config." }</h1>
+                            { self.view_config_formats() }
+                        </div>
+                        { self.view_rules() }
+                        { self.view_legacy_import() }
+                        <textarea
+                            class="col-body",
+                            id="config-input",
+                            value=&self.config,
+                            onfocus=|_| Msg::StartEditing,
+                            oninput=|e| Msg::PiiConfigChanged(e.value), />
+                        { self.view_lints() }
+                        { self.view_ineffective_rules() }
+                    </div>
+                </div>
+            </div>
+        }
+    }
+}
+
+impl PiiDemo {
+    fn view_output(&self) -> Html<Self> {
+        let diagnostics = if self.diagnostics.is_empty() {
+            "".into()
+        } else {
+            html! {
+                <ul class="diagnostics",>
+                    { for self.diagnostics.iter().map(Renderable::view) }
+                </ul>
+            }
+        };
+
+        html! {
+            <div class="items",>
+                { diagnostics }
+                {
+                    for self.output_items.iter().enumerate().map(|(index, item)| html! {
+                        <div class="item",>
+                            { self.view_item_header(index, item) }
+                            { self.view_item(index, item) }
+                        </div>
+                    })
+                }
+            </div>
+        }
+    }
+
+    fn view_item_header(&self, index: usize, item: &OutputItem<StrippedItem>) -> Html<Self> {
+        if !self.show_item_headers {
+            return "".into();
+        }
+
+        html! {
+            <h3 class="item-header",>
+                { format!("Item {}: {}", index + 1, item.ty()) }
+            </h3>
+        }
+    }
+
+    /// Shows the events as relay-general parsed and normalized them, next to the stripped output.
+    fn view_normalized_column(&self) -> Html<Self> {
+        if !self.show_normalized {
+            return "".into();
+        }
+
+        html! {
+            <div class="col",>
+                <div
+                    class="col-header",
+                    onclick=|_| Msg::StartEditing, >
+                    <h1>{ "The event as Relay sees it before stripping." }</h1>
+                </div>
+                <div class="col-body",>
+                    {
+                        for self.output_items.iter().enumerate().map(|(index, item)| html! {
+                            <div class="item",>
+                                { self.view_item_header(index, item) }
+                                { self.view_normalized_item(index, item) }
+                            </div>
+                        })
+                    }
+                </div>
+            </div>
+        }
+    }
+
+    fn view_normalized_item(&self, index: usize, item: &OutputItem<StrippedItem>) -> Html<Self> {
+        match *item {
+            OutputItem::Event { ref result, .. } => match *result {
+                Ok(ref stripped_item) => html! {
+                    <div>
+                        { view_parse_changes(stripped_item, self.normalize) }
+                        { (index, &stripped_item.input, ProcessingState::root()).view() }
+                    </div>
+                },
+                Err(ref e) => format!("ERROR: {}", e).into(),
+            },
+            OutputItem::Other { .. } => html! { <p class="item-skipped",>{ "Not processed" }</p> },
+        }
+    }
+
+    fn view_item(&self, index: usize, item: &OutputItem<StrippedItem>) -> Html<Self> {
+        match *item {
+            OutputItem::Event { ref result, .. } => {
+                let stripped_item = match *result {
+                    Ok(ref stripped_item) => stripped_item,
+                    Err(ref e) => return format!("ERROR: {}", e).into(),
+                };
+
+                match self.view_mode {
+                    ViewMode::Stripped => (index, &stripped_item.stripped, ProcessingState::root()).view(),
+                    ViewMode::Diff => {
+                        let changes = diff_values(&stripped_item.input, &stripped_item.stripped);
+                        (index, &changes[..]).view()
+                    }
+                }
+            }
+            OutputItem::Other {
+                ref filename,
+                size,
+                ..
+            } => {
+                let description = match *filename {
+                    Some(ref filename) => format!("{}, {} bytes, not processed", filename, size),
+                    None => format!("{} bytes, not processed", size),
+                };
+                html! { <p class="item-skipped",>{ description }</p> }
+            }
+        }
+    }
+
+    fn view_mode_toggle(&self) -> Html<Self> {
+        let normalize = self.normalize;
+        let show_normalized = self.show_normalized;
+        let class_for = |view_mode| {
+            if self.view_mode == view_mode {
+                "view-mode active"
+            } else {
+                "view-mode"
+            }
+        };
+
+        html! {
+            <div class="view-modes",>
+                <a
+                    class={ class_for(ViewMode::Stripped) },
+                    onclick=|_| Msg::SetViewMode(ViewMode::Stripped), >
+                    { "Stripped only" }
+                </a>
+                <a
+                    class={ class_for(ViewMode::Diff) },
+                    onclick=|_| Msg::SetViewMode(ViewMode::Diff), >
+                    { "Diff" }
+                </a>
+                <label
+                    class="show-normalized",
+                    title="Show the event as relay-general parsed it, before stripping, in a column of its own.", >
+                    <input
+                        type="checkbox",
+                        checked=self.show_normalized,
+                        onclick=|_| Msg::ShowNormalized(!show_normalized), />
+                    { "Show normalized" }
+                </label>
+                <label
+                    class="normalize",
+                    title="Run the normalization Relay applies before storing events, such as canonicalizing request headers, before stripping.", >
+                    <input
+                        type="checkbox",
+                        checked=self.normalize,
+                        onclick=|_| Msg::SetNormalize(!normalize), />
+                    { "Normalize like Relay" }
+                </label>
+            </div>
+        }
+    }
+
+    fn view_config_formats(&self) -> Html<Self> {
+        let format_class = |config_format| {
+            if self.config_format == config_format {
+                "config-format active"
+            } else {
+                "config-format"
+            }
+        };
+
+        html! {
+            <div class="config-formats",>
+                { "Format: " }
+                <a
+                    class={ format_class(None) },
+                    onclick=|_| Msg::SetConfigFormat(None), >
+                    { format!("auto ({})", self.effective_config_format) }
+                </a>
+                {
+                    for ConfigFormat::all().iter().cloned().map(|format| html! {
+                        <a
+                            class={ format_class(Some(format)) },
+                            onclick=|_| Msg::SetConfigFormat(Some(format)), >
+                            { format }
+                        </a>
+                    })
+                }
+                <br/>
+                { "Convert to: " }
+                {
+                    for ConfigFormat::all().iter().cloned().map(|format| html! {
+                        <a
+                            class="config-format",
+                            onclick=|_| Msg::ExportPiiConfig(format), >
+                            { format }
+                        </a>
+                    })
+                }
+            </div>
+        }
+    }
+
+    fn view_rules(&self) -> Html<Self> {
+        if let Some(ref rule_form) = self.rule_form {
+            return rule_form.view();
+        }
+
+        let rules = self
+            .pii_config
+            .as_ref()
+            .ok()
+            .and_then(|config| config.0.get("rules").and_then(|x| x.as_object()).cloned())
+            .unwrap_or_default();
+
+        html! {
+            <div class="rules",>
+                { "Rules: " }
+                {
+                    for rules.keys().map(|rule| {
+                        let rule = rule.clone();
+                        html! {
+                            <a
+                                class="rule",
+                                onclick=|_| Msg::EditRule(Some(rule.clone())), >
+                                <code>{ &rule }</code>
+                            </a>
+                        }
+                    })
+                }
+                <button onclick=|_| Msg::EditRule(None), >{ "New rule" }</button>
+                <button
+                    title="Convert the data scrubbing settings of a project to a PII config",
+                    onclick=|_| Msg::OpenLegacyImport, >
+                    { "Import legacy settings" }
+                </button>
+            </div>
+        }
+    }
+
+    fn view_legacy_import(&self) -> Html<Self> {
+        let settings = match self.legacy_settings {
+            Some(ref settings) => settings,
+            None => return "".into(),
+        };
+
+        html! {
+            <div class="legacy-import",>
+                <p>{ "Paste the data scrubbing settings of a project. Importing replaces the current config." }</p>
+                <textarea
+                    value=settings,
+                    oninput=|e| Msg::LegacySettingsChanged(e.value), />
+                <button onclick=|_| Msg::ImportLegacySettings, >{ "Import" }</button>
+                <button onclick=|_| Msg::CloseLegacyImport, >{ "Cancel" }</button>
+            </div>
+        }
+    }
+
+    fn view_lints(&self) -> Html<Self> {
+        if self.lints.is_empty() {
+            return "".into();
+        }
+
+        html! {
+            <ul class="lints",>
+                { for self.lints.iter().map(|lint| html! { <li class="lint",>{ lint }</li> }) }
+            </ul>
+        }
+    }
+
+    fn view_progress(&self) -> Html<Self> {
+        let text = match self.state {
+            State::SearchingPiiRules { .. } => "Searching for rules…",
+            _ if self.stripping => "Stripping…",
+            _ => return "".into(),
+        };
+
+        html! { <span class="progress",>{ text }</span> }
+    }
+
+    fn view_ineffective_rules(&self) -> Html<Self> {
+        if self.ineffective_rules_request_id.is_some() {
+            return html! {
+                <div class="ineffective-rules",>{ "Looking for rules without effect…" }</div>
+            };
+        }
+
+        let ineffective_rules = match self.ineffective_rules {
+            Some(ref ineffective_rules) => ineffective_rules,
+            None => {
+                return html! {
+                    <div class="ineffective-rules",>
+                        <button onclick=|_| Msg::FindIneffectiveRules, >
+                            { "Find rules without effect" }
+                        </button>
+                    </div>
+                };
+            }
+        };
+
+        if ineffective_rules.is_empty() {
+            return html! {
+                <div class="ineffective-rules",>{ "Every rule changes the stripped events." }</div>
+            };
+        }
+
+        html! {
+            <div class="ineffective-rules",>
+                { "Removing these changes nothing in the stripped events:" }
+                <ul>
+                    {
+                        for ineffective_rules.iter().map(|ineffective_rule| {
+                            let config = ineffective_rule.config.clone();
+                            html! {
+                                <li>
+                                    <code>{ &ineffective_rule.rule }</code>
+                                    { " on " }
+                                    <code>{ &ineffective_rule.selector }</code>
+                                    { " " }
+                                    <a
+                                        class="remove-rule",
+                                        onclick=|_| Msg::ApplyPiiConfig(config.clone()), >
+                                        { "remove" }
+                                    </a>
+                                </li>
+                            }
+                        })
+                    }
+                </ul>
+            </div>
+        }
+    }
+
+    fn view_pins(&self) -> Html<Self> {
+        if self.pins.is_empty() {
+            return "".into();
+        }
+
+        html! {
+            <div class="pins",>
+                <h3>{ "Pinned values" }</h3>
+                <ul>
+                    {
+                        for self.pins.iter().map(|path| {
+                            let unpin_path = path.clone();
+                            html! {
+                                <li>
+                                    <code>{ path }</code>
+                                    <a
+                                        class="unpin",
+                                        title="Unpin value",
+                                        onclick=|_| Msg::UnpinValue(unpin_path.clone()), >
+                                        { "×" }
+                                    </a>
+                                </li>
+                            }
+                        })
+                    }
+                </ul>
+            </div>
+        }
+    }
+
+    fn view_fixtures(&self) -> Html<Self> {
+        let results = match self.fixture_results {
+            _ if self.run_fixtures_request_id.is_some() => html! {
+                <p class="progress",>{ "Running fixtures…" }</p>
+            },
+            Some(ref results) => {
+                let failed = results
+                    .iter()
+                    .filter(|(_, result)| result.as_ref().map(|changes| !changes.is_empty()).unwrap_or(true))
+                    .count();
+
+                html! {
+                    <div class="fixture-results",>
+                        <p>{ format!("{} of {} fixtures produce different output.", failed, results.len()) }</p>
+                        { for results.iter().map(|(name, result)| view_fixture_result(name, result)) }
+                    </div>
+                }
+            }
+            None => "".into(),
+        };
+
+        html! {
+            <div class="fixtures",>
+                <h3>{ "Fixtures" }</h3>
+                <input
+                    type="text",
+                    placeholder="Fixture name",
+                    value=&self.fixture_name,
+                    oninput=|e| Msg::FixtureNameChanged(e.value), />
+                <button
+                    title="Save the event with the current config and output",
+                    onclick=|_| Msg::SaveFixture, >
+                    { "Save" }
+                </button>
+                <button
+                    title="Check that the current config still produces the saved output",
+                    onclick=|_| Msg::RunFixtures, >
+                    { "Run" }
+                </button>
+                <ul>
+                    {
+                        for self.fixtures.keys().map(|name| {
+                            let copy_name = name.clone();
+                            let delete_name = name.clone();
+                            html! {
+                                <li>
+                                    <a
+                                        class="fixture",
+                                        title="Copy as JSON, for the fixtures directory",
+                                        onclick=|_| Msg::CopyFixture(copy_name.clone()), >
+                                        { name }
+                                    </a>
+                                    <a
+                                        class="delete-fixture",
+                                        title="Delete fixture",
+                                        onclick=|_| Msg::DeleteFixture(delete_name.clone()), >
+                                        { "×" }
+                                    </a>
+                                </li>
+                            }
+                        })
+                    }
+                </ul>
+                { results }
+            </div>
+        }
+    }
+
+    fn view_workspaces(&self) -> Html<Self> {
+        html! {
+            <div class="workspaces",>
+                <input
+                    type="text",
+                    placeholder="Workspace name",
+                    value=&self.workspace_name,
+                    oninput=|e| Msg::WorkspaceNameChanged(e.value), />
+                <button onclick=|_| Msg::SaveWorkspace, >{ "Save" }</button>
+                <ul>
+                    {
+                        for self.workspaces.keys().map(|name| {
+                            let load_name = name.clone();
+                            let delete_name = name.clone();
+                            html! {
+                                <li>
+                                    <a
+                                        class="workspace",
+                                        onclick=|_| Msg::LoadWorkspace(load_name.clone()), >
+                                        { name }
+                                    </a>
+                                    <a
+                                        class="delete-workspace",
+                                        title="Delete workspace",
+                                        onclick=|_| Msg::DeleteWorkspace(delete_name.clone()), >
+                                        { "×" }
+                                    </a>
+                                </li>
+                            }
+                        })
+                    }
+                </ul>
+            </div>
+        }
+    }
+}
+
+impl Renderable<PiiDemo> for Diagnostic {
+    fn view(&self) -> Html<PiiDemo> {
+        let source = self.source;
+
+        match self.location {
+            Some((line, column)) => html! {
+                <li class="diagnostic",>
+                    <a onclick=|_| Msg::GoToDiagnostic(source, line, column), >
+                        { format!("{} line {}, column {}", source, line, column) }
+                    </a>
+                    { ": " }{ &self.message }
+                </li>
+            },
+            None => html! {
+                <li class="diagnostic",>{ format!("{}: {}", source, self.message) }</li>
+            },
+        }
+    }
+}
+
+impl Renderable<PiiDemo> for RedactionTrace {
+    fn view(&self) -> Html<PiiDemo> {
+        html! {
+            <li class="trace",>
+                { "On " }<code>{ &self.selector }</code>{ ": " }
+                {
+                    for self.rules.iter().enumerate().map(|(i, rule)| html! {
+                        <span>
+                            { if i > 0 { " → " } else { "" } }
+                            <code>{ rule }</code>
+                        </span>
+                    })
+                }
+                { " (" }{ &self.method }{ ")" }
+            </li>
+        }
+    }
+}
+
+fn view_rule_field(label: &str, field: RuleField, value: &str) -> Html<PiiDemo> {
+    html! {
+        <label class="rule-field",>
+            <span>{ label }</span>
+            <input
+                type="text",
+                value=value,
+                oninput=|e| Msg::RuleFormChanged(field, e.value), />
+        </label>
+    }
+}
+
+fn view_rule_choices(label: &str, field: RuleField, choices: &[&'static str], current: &str) -> Html<PiiDemo> {
+    html! {
+        <div class="rule-field",>
+            <span>{ label }</span>
+            {
+                for choices.iter().cloned().map(|choice| html! {
+                    <a
+                        class={ if choice == current { "rule-choice active" } else { "rule-choice" } },
+                        onclick=|_| Msg::RuleFormChanged(field, choice.to_owned()), >
+                        { choice }
+                    </a>
+                })
+            }
+        </div>
+    }
+}
+
+impl Renderable<PiiDemo> for RuleForm {
+    fn view(&self) -> Html<PiiDemo> {
+        let type_fields = match &*self.ty {
+            "pattern" => view_rule_field("Pattern", RuleField::Pattern, &self.pattern),
+            "redact_pair" => view_rule_field("Key pattern", RuleField::KeyPattern, &self.key_pattern),
+            "alias" => view_rule_field("Rule", RuleField::Rule, &self.rule),
+            "multiple" => view_rule_field("Rules (comma-separated)", RuleField::Rules, &self.rules),
+            _ => "".into(),
+        };
+
+        let hide_inner = if self.ty == "alias" || self.ty == "multiple" {
+            let hide_inner = !self.hide_inner;
+            html! {
+                <label class="rule-field",>
+                    <span>{ "Hide inner rules" }</span>
+                    <input
+                        type="checkbox",
+                        checked=self.hide_inner,
+                        onclick=|_| Msg::RuleFormChanged(RuleField::HideInner, hide_inner.to_string()), />
+                </label>
+            }
+        } else {
+            "".into()
+        };
+
+        let redaction_fields = match &*self.method {
+            "replace" => view_rule_field("Replacement text", RuleField::Text, &self.text),
+            "mask" => view_rule_field("Mask char", RuleField::MaskChar, &self.mask_char),
+            "hash" => view_rule_field("Hash key", RuleField::Key, &self.key),
+            _ => "".into(),
+        };
+
+        html! {
+            <div class="rule-form",>
+                { view_rule_field("Id", RuleField::Id, &self.id) }
+                { view_rule_field("Apply on", RuleField::Selector, &self.selector) }
+                { view_rule_choices("Type", RuleField::Type, RULE_TYPES, &self.ty) }
+                { type_fields }
+                { hide_inner }
+                { view_rule_choices("Redaction", RuleField::Method, REDACTION_METHODS, &self.method) }
+                { redaction_fields }
+                {
+                    match self.error {
+                        Some(ref error) => html! { <p class="rule-error",>{ error }</p> },
+                        None => "".into(),
+                    }
+                }
+                <button onclick=|_| Msg::CloseRuleForm, >{ "Done" }</button>
+            </div>
+        }
+    }
+}
+
+impl Renderable<PiiDemo> for State {
+    fn view(&self) -> Html<PiiDemo> {
+        match *self {
+            State::Editing => "".into(),
+            State::SearchingPiiRules { ref request } => html! {
+                <div class="choose-rule",>
+                    <div class="choose-rule-header",>
+                    { request.view() }
+                    <p class="progress",>{ "Searching for rules…" }</p>
+                    </div>
+                </div>
+            },
+            State::SelectPiiRule {
+                ref request,
+                ref suggestions,
+                ref traces,
+            } => {
+                // Keep the sections in the order of the suggestions, which puts narrow selectors first.
+                let mut sections: Vec<(&str, Vec<&PiiRuleSuggestion>)> = vec![];
+                for suggestion in &suggestions.suggestions {
+                    match sections.iter().position(|&(pii_kind, _)| pii_kind == suggestion.pii_kind()) {
+                        Some(index) => sections[index].1.push(suggestion),
+                        None => sections.push((suggestion.pii_kind(), vec![suggestion])),
+                    }
+                }
+
+                let traces = if traces.is_empty() {
+                    "".into()
+                } else {
+                    html! {
+                        <div class="traces",>
+                            <h3>{ "Redacted by" }</h3>
+                            <ul>
+                                { for traces.iter().map(Renderable::view) }
+                            </ul>
+                        </div>
+                    }
+                };
+
+                let cover = match suggestions.cover {
+                    Some(ref cover) => cover.view(),
+                    None => "".into(),
+                };
+
+                let paths = request.paths.clone();
+                let pin = html! {
+                    <button
+                        class="pin",
+                        title="Suggestions will not change pinned values",
+                        onclick=|_| Msg::PinValues(paths.clone()), >
+                        { "Keep" }
+                    </button>
+                };
+
+                let rejected = match suggestions.rejected {
+                    0 => "".into(),
+                    1 => html! { <p class="rejected",>{ "1 suggestion hidden because it changes pinned values" }</p> },
+                    n => html! {
+                        <p class="rejected",>{ format!("{} suggestions hidden because they change pinned values", n) }</p>
+                    },
+                };
+
+                if suggestions.suggestions.is_empty() && suggestions.cover.is_none() {
+                    html! {
+                        <div class="choose-rule",>
+                            <div class="choose-rule-header",>
+                            { pin }
+                            { traces }
+                            <strong>{ "Sorry, we don't know how to match this." }</strong>
+                            { rejected }
+                            <p>{ "Click anywhere else to close" }</p>
+                            </div>
+                        </div>
+                    }
+                } else {
+                    html! {
+                        <div class="choose-rule",>
+                            <div class="choose-rule-header",>
+                            { pin }
+                            { request.view() }
+                            { traces }
+                            { rejected }
+                            <p>{ "Click anywhere else to close, shift-click values to select several" }</p>
+                            </div>
+                            { cover }
+                            {
+                                for sections.iter().map(|(pii_kind, suggestions)| html! {
+                                    <div class="pii-kind-section",>
+                                        <h3>{ "On "}{ pii_kind }</h3>
+                                        <ul>
+                                            { for suggestions.iter().cloned().map(Renderable::view) }
+                                        </ul>
+                                    </div>
+                                })
+                            }
+                        </div>
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl<'a> Renderable<PiiDemo> for (usize, &StrippedEvent, &'a ProcessingState<'a>) {
+    fn view(&self) -> Html<PiiDemo> {
+        let (item, ref annotated, ref state) = *self;
+
+        let path = format!("{}", state.path());
+
+        let strippable_value = |html| {
+            html! {
+                <a class="strippable",
+                    onclick=|e| select_value(&e, item, path.clone()), >
+                    { html }
+                </a>
+            }
+        };
+
+        let remarks: Vec<Remark> = annotated.meta().iter_remarks().cloned().collect();
+
+        let mut value = match annotated.value() {
+            Some(&Value::Object(ref map)) => html! {
+                <ul class="json map",>
+                    {
+                        for map.iter().map(|(k, v)| {
+                            let inner_state = state.enter_borrowed(k, state.inner_attrs(), None);
+                            let path = format!("{}", inner_state.path());
+                            html! {
+                                <li>
+                                    <a class="strippable",
+                                        onclick=|e| select_value(&e, item, path.clone()), >
+                                        <span class="json key",>{ serde_json::to_string(k).unwrap() }</span>
+                                    </a>
+                                    { ": " }{ (item, v, &inner_state).view() }
+                                </li>
+                            }
+                        })
+                    }
+                </ul>
+            },
+            Some(&Value::Array(ref values)) => html! {
+                <ul class="json array",>
+                    {
+                        for values.iter().enumerate().map(move |(i, v)| {
+                            let inner_state = state.enter_index(i, state.inner_attrs(), None);
+
+                            html! {
+                                <li class="json element",>{ (item, v, &inner_state).view() }</li>
+                            }
+                        })
+                    }
+                </ul>
+            },
+            Some(&Value::String(ref string)) => strippable_value(view_string(string, &remarks)),
+            Some(&Value::U64(number)) => {
+                strippable_value(html! { <span class="json number",>{ number }</span> })
+            }
+            Some(&Value::I64(number)) => {
+                strippable_value(html! { <span class="json number",>{ number }</span> })
+            }
+            Some(&Value::F64(number)) => {
+                strippable_value(html! { <span class="json number",>{ number }</span> })
+            }
+            Some(&Value::Bool(number)) => {
+                strippable_value(html! { <span class="json boolean",>{ number }</span> })
+            }
+            None => {
+                strippable_value(html! { <span class="json null",>{ "null" }</span> })
+            }
+        };
+
+        if !annotated.meta().is_empty() {
+            let meta = annotated.meta();
+
+            value = html! {
+                <span class="annotated",>
+                    <small class="meta",>
+                        <div class="remarks",>
+                            { for remarks.iter().map(Renderable::view) }
+                        </div>
+                        {
+                            if meta.iter_errors().next().is_some() {
+                                html! {
+                                    <div class="errors",>
+                                        {
+                                            serde_json::to_string(&meta.iter_errors().collect::<Vec<_>>()).unwrap()
+                                        }
+                                    </div>
+                                }
+                            } else {
+                                "".into()
+                            }
+                        }
+                    </small>
+                    { value }
+                </span>
+            }
+        }
+
+        value
+    }
+}
+
+/// Renders a JSON string, highlighting the parts of it that remarks point to.
+fn view_string(string: &str, remarks: &[Remark]) -> Html<PiiDemo> {
+    let escape = |segment: &str| {
+        let quoted = serde_json::to_string(segment).unwrap();
+        quoted[1..quoted.len() - 1].to_owned()
+    };
+
+    html! {
+        <span class="json string",>
+            { "\"" }
+            {
+                for split_by_remarks(string, remarks).into_iter().map(|(segment, remark)| match remark {
+                    Some(remark) => html! {
+                        <mark
+                            class={ format!("remark-range {}", remark_type_name(remark.ty())) },
+                            title={ remark.rule_id() }, >
+                            { escape(&segment) }
+                        </mark>
+                    },
+                    None => escape(&segment).into(),
+                })
+            }
+            { "\"" }
+        </span>
+    }
+}
+
+impl Renderable<PiiDemo> for Remark {
+    fn view(&self) -> Html<PiiDemo> {
+        let ty = remark_type_name(self.ty());
+
+        html! {
+            <span class={ format!("remark {}", ty) },>
+                <code class="rule-id",>{ self.rule_id() }</code>
+                <span class="remark-type",>{ ty }</span>
+                {
+                    match self.range() {
+                        Some(&(start, end)) => html! {
+                            <span class="remark-range-label",>{ format!("{}..{}", start, end) }</span>
+                        },
+                        None => "".into(),
+                    }
+                }
+            </span>
+        }
+    }
+}
+
+impl<'a> Renderable<PiiDemo> for (usize, &'a Change) {
+    fn view(&self) -> Html<PiiDemo> {
+        let (item, change) = *self;
+        let path = change.path.clone();
+
+        html! {
+            <tr
+                class={ format!("change {}", change.kind.as_str()) },
+                onclick=|e| select_value(&e, item, path.clone()), >
+                <td class="path",><code>{ &change.path }</code></td>
+                <td class="json original",>{ change.original.payload_to_json().unwrap_or_default() }</td>
+                <td class="json stripped",>{ change.stripped.payload_to_json().unwrap_or_default() }</td>
+                <td class="kind",>{ change.kind.as_str() }</td>
+            </tr>
+        }
+    }
+}
+
+impl<'a> Renderable<PiiDemo> for (usize, &'a [Change]) {
+    fn view(&self) -> Html<PiiDemo> {
+        let (item, changes) = *self;
+
+        if changes.is_empty() {
+            html! {
+                <p>{ "Nothing was stripped from this event." }</p>
+            }
+        } else {
+            html! {
+                <table class="diff",>
+                    <tr>
+                        <th>{ "Path" }</th>
+                        <th>{ "Original" }</th>
+                        <th>{ "Stripped" }</th>
+                        <th></th>
+                    </tr>
+                    { for changes.iter().map(|change| (item, change).view()) }
+                </table>
+            }
+        }
+    }
+}
+
+pub fn main() {
+    yew::initialize();
+    // The worker is loaded from the same script as the page.
+    if is_worker() {
+        Worker::register();
+    } else {
+        App::<PiiDemo>::new().mount_to_body();
+    }
+    yew::run_loop();
+}
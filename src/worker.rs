@@ -0,0 +1,443 @@
+use std::collections::BTreeMap;
+use std::mem;
+use std::time::Duration;
+
+use failure::{err_msg, Error};
+use stdweb::unstable::TryInto;
+use yew::agent::{Agent, AgentLink, HandlerId, Public};
+use yew::services::timeout::{TimeoutService, TimeoutTask};
+
+use relay_general::types::{Annotated, Value};
+
+use config_format::{parse_config_as, ConfigFormat};
+use diff::{diff_values, Change};
+use envelope::{is_envelope, parse_input, InputItem};
+use fixtures::Fixture;
+use lint::lint_config;
+use suggestions::{get_ineffective_rules, get_rule_suggestions_for_values, IneffectiveRule, RuleSuggestions};
+use trace::{trace_redactions, RedactionTrace};
+use types::{get_other_paths, normalize_event, to_value_tree, CompiledPiiConfig, PiiConfig, SensitiveEvent};
+use validation::{validate, Diagnostic};
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct StripRequest {
+    pub id: u64,
+    pub event: String,
+    /// The text of the config editor. The worker validates it along with the event.
+    pub config: String,
+    /// The format of the config, or `None` to detect it from the input.
+    pub config_format: Option<ConfigFormat>,
+    /// Whether to normalize events like Relay before stripping them.
+    pub normalize: bool,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct SuggestRequest {
+    pub id: u64,
+    pub event: String,
+    pub config: PiiConfig,
+    /// The index of the input item the values belong to.
+    pub item: usize,
+    pub paths: Vec<String>,
+    pub pins: Vec<String>,
+    pub normalize: bool,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct IneffectiveRulesRequest {
+    pub id: u64,
+    pub event: String,
+    pub config: PiiConfig,
+    pub normalize: bool,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct RecordFixturesRequest {
+    pub id: u64,
+    pub event: String,
+    pub config: PiiConfig,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct RunFixturesRequest {
+    pub id: u64,
+    pub fixtures: BTreeMap<String, Fixture>,
+    pub config: PiiConfig,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub enum Request {
+    Strip(StripRequest),
+    Suggest(SuggestRequest),
+    FindIneffectiveRules(IneffectiveRulesRequest),
+    RecordFixtures(RecordFixturesRequest),
+    RunFixtures(RunFixturesRequest),
+}
+
+impl Request {
+    pub fn id(&self) -> u64 {
+        match *self {
+            Request::Strip(ref request) => request.id,
+            Request::Suggest(ref request) => request.id,
+            Request::FindIneffectiveRules(ref request) => request.id,
+            Request::RecordFixtures(ref request) => request.id,
+            Request::RunFixtures(ref request) => request.id,
+        }
+    }
+}
+
+/// An input event before and after stripping.
+#[derive(Serialize, Deserialize)]
+pub struct StrippedItem {
+    /// The JSON of the event as it was passed to the PII processor, including `_meta`.
+    pub input: String,
+    /// The JSON of the stripped event, including `_meta`.
+    pub stripped: String,
+    /// The values of the pasted JSON that relay-general dropped, changed or added while parsing
+    /// the event.
+    pub parse_changes: Vec<Change>,
+    /// The values of the parsed event that normalization changed, empty if it is disabled.
+    pub normalization_changes: Vec<Change>,
+    /// The paths of attributes that relay-general does not know and kept in `other`.
+    pub other_paths: Vec<String>,
+}
+
+/// An item of the input as it is shown in the output column.
+#[derive(Serialize, Deserialize)]
+pub enum OutputItem<T> {
+    Event {
+        ty: String,
+        result: Result<T, String>,
+    },
+    Other {
+        ty: String,
+        filename: Option<String>,
+        size: usize,
+    },
+}
+
+impl<T> OutputItem<T> {
+    pub fn ty(&self) -> &str {
+        match *self {
+            OutputItem::Event { ref ty, .. } => ty,
+            OutputItem::Other { ref ty, .. } => ty,
+        }
+    }
+
+    /// Converts the result of an event item.
+    pub fn map<U, F>(self, f: F) -> OutputItem<U>
+    where
+        F: FnOnce(T) -> Result<U, Error>,
+    {
+        match self {
+            OutputItem::Event { ty, result } => OutputItem::Event {
+                ty,
+                result: result.and_then(|x| f(x).map_err(|e| e.to_string())),
+            },
+            OutputItem::Other { ty, filename, size } => OutputItem::Other { ty, filename, size },
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub enum Response {
+    Stripped {
+        id: u64,
+        /// Problems with the event and config input.
+        diagnostics: Vec<Diagnostic>,
+        /// Whether the input is an envelope rather than a bare event.
+        envelope: bool,
+        /// Every input item, empty if the event or config input is invalid.
+        items: Vec<OutputItem<StrippedItem>>,
+        lints: Vec<String>,
+    },
+    Suggested {
+        id: u64,
+        result: Result<(RuleSuggestions, Vec<RedactionTrace>), String>,
+    },
+    IneffectiveRules {
+        id: u64,
+        result: Result<Vec<IneffectiveRule>, String>,
+    },
+    FixturesRecorded {
+        id: u64,
+        /// One fixture per event of the input.
+        result: Result<Vec<Fixture>, String>,
+    },
+    FixturesRun {
+        id: u64,
+        /// The values that differ from the expected output, by fixture name.
+        results: Vec<(String, Result<Vec<Change>, String>)>,
+    },
+}
+
+impl Response {
+    /// The id of the request this responds to.
+    pub fn id(&self) -> u64 {
+        match *self {
+            Response::Stripped { id, .. } => id,
+            Response::Suggested { id, .. } => id,
+            Response::IneffectiveRules { id, .. } => id,
+            Response::FixturesRecorded { id, .. } => id,
+            Response::FixturesRun { id, .. } => id,
+        }
+    }
+}
+
+pub enum Msg {
+    Process,
+}
+
+/// Strips events, searches for suggestions and runs other analyses off the main thread.
+///
+/// Only the latest request of each kind is processed. Requests are collected until the worker is
+/// idle, so anything that was superseded while a previous request was running is dropped without
+/// being computed. A request that is already running cannot be interrupted, so the page starts a
+/// new worker instead when it replaces one.
+pub struct Worker {
+    link: AgentLink<Worker>,
+    timeout: TimeoutService,
+    task: Option<TimeoutTask>,
+    pending: Vec<(HandlerId, Request)>,
+}
+
+impl Agent for Worker {
+    type Reach = Public;
+    type Message = Msg;
+    type Input = Request;
+    type Output = Response;
+
+    fn create(link: AgentLink<Self>) -> Self {
+        Worker {
+            link,
+            timeout: TimeoutService::new(),
+            task: None,
+            pending: vec![],
+        }
+    }
+
+    fn update(&mut self, msg: Self::Message) {
+        match msg {
+            Msg::Process => {
+                self.task = None;
+
+                for (who, request) in mem::replace(&mut self.pending, vec![]) {
+                    self.link.response(who, process(request));
+                }
+            }
+        }
+    }
+
+    fn handle(&mut self, request: Self::Input, who: HandlerId) {
+        let kind = mem::discriminant(&request);
+        self.pending.retain(|(_, pending)| mem::discriminant(pending) != kind);
+        self.pending.push((who, request));
+
+        // Yield to the event loop first, so that requests which are already queued replace this
+        // one before any work is done.
+        if self.task.is_none() {
+            let callback = self.link.send_back(|_| Msg::Process);
+            self.task = Some(self.timeout.spawn(Duration::from_millis(0), callback));
+        }
+    }
+
+    fn name_of_resource() -> &'static str {
+        "piinguin.js"
+    }
+}
+
+/// Returns whether the code runs in a web worker rather than on the page.
+pub fn is_worker() -> bool {
+    js!(return typeof window === "undefined";).try_into().unwrap_or(false)
+}
+
+/// Returns the current time in RFC 3339 format. It is read in JavaScript because there is no clock
+/// on `wasm32-unknown-unknown`.
+fn get_received_at() -> String {
+    js!(return new Date().toISOString();).try_into().unwrap_or_default()
+}
+
+fn process(request: Request) -> Response {
+    match request {
+        Request::Strip(request) => strip(request),
+        Request::Suggest(request) => suggest(request),
+        Request::FindIneffectiveRules(request) => find_ineffective_rules(request),
+        Request::RecordFixtures(request) => record_fixtures(request),
+        Request::RunFixtures(request) => run_fixtures(request),
+    }
+}
+
+fn get_events(input: &str) -> Result<Vec<SensitiveEvent>, Error> {
+    Ok(parse_input(input)?
+        .into_iter()
+        .filter_map(|item| match item {
+            InputItem::Event { event, .. } => Some(event),
+            InputItem::Other { .. } => None,
+        })
+        .collect())
+}
+
+fn strip(request: StripRequest) -> Response {
+    let diagnostics = validate(&request.event, &request.config, request.config_format);
+    let envelope = is_envelope(&request.event);
+
+    let input = parse_input(&request.event)
+        .and_then(|input_items| Ok((input_items, parse_config_as(&request.config, request.config_format)?)));
+
+    let (input_items, config) = match input {
+        Ok(input) => input,
+        // The diagnostics explain what is wrong with the input.
+        Err(_) => {
+            return Response::Stripped {
+                id: request.id,
+                diagnostics,
+                envelope,
+                items: vec![],
+                lints: vec![],
+            }
+        }
+    };
+
+    let compiled = config.compile();
+    let mut events = vec![];
+    let mut items = vec![];
+
+    for input_item in input_items {
+        match input_item {
+            InputItem::Event { ty, mut event, payload } => {
+                let result = match compiled {
+                    Ok(ref compiled) => strip_event(compiled, &mut event, &payload, request.normalize),
+                    Err(ref e) => Err(err_msg(e.to_string())),
+                };
+                items.push(OutputItem::Event {
+                    ty,
+                    result: result.map_err(|e| e.to_string()),
+                });
+                events.push(event);
+            }
+            InputItem::Other { ty, filename, size } => items.push(OutputItem::Other { ty, filename, size }),
+        }
+    }
+
+    let lints = match lint_config(&config, &events) {
+        Ok(lints) => lints.iter().map(ToString::to_string).collect(),
+        Err(e) => vec![format!("ERROR: {}", e)],
+    };
+
+    Response::Stripped {
+        id: request.id,
+        diagnostics,
+        envelope,
+        items,
+        lints,
+    }
+}
+
+/// Normalizes the event in place if requested, and strips it.
+fn strip_event(
+    config: &CompiledPiiConfig,
+    event: &mut SensitiveEvent,
+    payload: &str,
+    normalize: bool,
+) -> Result<StrippedItem, Error> {
+    // Compare serialized events, so that only the changes made by relay-general show up and not
+    // the differences between its types and plain JSON values.
+    let raw = Annotated::<Value>::from_json(payload)?;
+    let parsed = Annotated::<Value>::from_json(&event.to_json()?)?;
+    let parse_changes = diff_values(&raw, &parsed);
+
+    let normalization_changes = if normalize {
+        normalize_event(event, &get_received_at())?;
+        diff_values(&parsed, &Annotated::<Value>::from_json(&event.to_json()?)?)
+    } else {
+        vec![]
+    };
+
+    let input = to_value_tree(event.clone());
+    let stripped = config.strip_event(event)?;
+
+    Ok(StrippedItem {
+        parse_changes,
+        normalization_changes,
+        other_paths: get_other_paths(event)?,
+        input: input.to_json()?,
+        stripped: stripped.to_json()?,
+    })
+}
+
+fn suggest(request: SuggestRequest) -> Response {
+    let result = || -> Result<_, Error> {
+        let mut event = match parse_input(&request.event)?.into_iter().nth(request.item) {
+            Some(InputItem::Event { event, .. }) => event,
+            _ => return Err(err_msg("Input item is not an event")),
+        };
+
+        if request.normalize {
+            normalize_event(&mut event, &get_received_at())?;
+        }
+
+        let suggestions = get_rule_suggestions_for_values(&event, &request.config, &request.paths, &request.pins)?;
+
+        let mut traces = vec![];
+        for path in &request.paths {
+            for trace in trace_redactions(&event, &request.config, path)? {
+                if !traces.contains(&trace) {
+                    traces.push(trace);
+                }
+            }
+        }
+
+        Ok((suggestions, traces))
+    };
+
+    Response::Suggested {
+        id: request.id,
+        result: result().map_err(|e| e.to_string()),
+    }
+}
+
+fn find_ineffective_rules(request: IneffectiveRulesRequest) -> Response {
+    let result = || -> Result<_, Error> {
+        let mut events = get_events(&request.event)?;
+
+        if request.normalize {
+            for event in &mut events {
+                normalize_event(event, &get_received_at())?;
+            }
+        }
+
+        get_ineffective_rules(&events, &request.config.compile()?)
+    };
+
+    Response::IneffectiveRules {
+        id: request.id,
+        result: result().map_err(|e| e.to_string()),
+    }
+}
+
+fn record_fixtures(request: RecordFixturesRequest) -> Response {
+    let result = get_events(&request.event).and_then(|events| {
+        events
+            .iter()
+            .map(|event| Fixture::record(event, &request.config))
+            .collect::<Result<Vec<_>, _>>()
+    });
+
+    Response::FixturesRecorded {
+        id: request.id,
+        result: result.map_err(|e| e.to_string()),
+    }
+}
+
+fn run_fixtures(request: RunFixturesRequest) -> Response {
+    let results = request
+        .fixtures
+        .iter()
+        .map(|(name, fixture)| (name.clone(), fixture.run(Some(&request.config)).map_err(|e| e.to_string())))
+        .collect();
+
+    Response::FixturesRun {
+        id: request.id,
+        results,
+    }
+}
@@ -0,0 +1,70 @@
+use std::collections::BTreeMap;
+
+use yew::format::Json;
+use yew::services::storage::{Area, StorageService};
+
+use fixtures::Fixture;
+
+static CURRENT_KEY: &'static str = "piinguin.workspace";
+static SAVED_KEY: &'static str = "piinguin.workspaces";
+static FIXTURES_KEY: &'static str = "piinguin.fixtures";
+
+/// The contents of the editors, as persisted in `localStorage`.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Workspace {
+    pub event: String,
+    pub config: String,
+    /// Paths of values that suggestions must not change.
+    #[serde(default)]
+    pub pins: Vec<String>,
+    /// Whether to normalize events like Relay before stripping them.
+    #[serde(default)]
+    pub normalize: bool,
+}
+
+pub struct WorkspaceStorage {
+    storage: StorageService,
+}
+
+impl WorkspaceStorage {
+    pub fn new() -> Self {
+        WorkspaceStorage {
+            storage: StorageService::new(Area::Local),
+        }
+    }
+
+    /// Returns the workspace that was open when the page was last left.
+    pub fn load_current(&self) -> Option<Workspace> {
+        match self.storage.restore(CURRENT_KEY) {
+            Json(Ok(workspace)) => Some(workspace),
+            _ => None,
+        }
+    }
+
+    pub fn store_current(&mut self, workspace: &Workspace) {
+        self.storage.store(CURRENT_KEY, Json(workspace));
+    }
+
+    /// Returns all workspaces the user saved under a name.
+    pub fn load_saved(&self) -> BTreeMap<String, Workspace> {
+        match self.storage.restore(SAVED_KEY) {
+            Json(Ok(workspaces)) => workspaces,
+            _ => BTreeMap::new(),
+        }
+    }
+
+    pub fn store_saved(&mut self, workspaces: &BTreeMap<String, Workspace>) {
+        self.storage.store(SAVED_KEY, Json(workspaces));
+    }
+
+    pub fn load_fixtures(&self) -> BTreeMap<String, Fixture> {
+        match self.storage.restore(FIXTURES_KEY) {
+            Json(Ok(fixtures)) => fixtures,
+            _ => BTreeMap::new(),
+        }
+    }
+
+    pub fn store_fixtures(&mut self, fixtures: &BTreeMap<String, Fixture>) {
+        self.storage.store(FIXTURES_KEY, Json(fixtures));
+    }
+}